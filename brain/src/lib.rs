@@ -1,6 +1,5 @@
 extern crate fnv;
 extern crate heuristic;
-extern crate indexmap;
 extern crate itertools;
 extern crate nalgebra as na;
 pub extern crate predict;
@@ -12,6 +11,7 @@ extern crate lazy_static;
 
 pub mod plan;
 pub mod play;
+pub mod strategy;
 
 pub use heuristic::*;
 
@@ -1,16 +1,14 @@
 use heuristic::HeuristicModel;
 use na::{Point3, Unit, Vector3};
-use predict;
+use predict::{self, arena::Arena, PredictError};
 use state::*;
 use std::cmp::Ordering;
-use std::error::Error;
 use std::f32::consts::PI;
 
-use indexmap::map::Entry::{Occupied, Vacant};
-use indexmap::IndexMap;
 use itertools;
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
 use std::mem;
+use std::time::{Duration, Instant};
 use std::usize;
 
 use fnv::FnvHasher;
@@ -118,21 +116,49 @@ lazy_static! {
             //straight_drift_reverse,
         ]
     };
+
+    pub static ref GROUND_NO_BOOST_CONTROL_BRANCHES: Vec<BrickControllerState> =
+        GROUND_CONTROL_BRANCHES.iter().filter(|controller| !controller.boost).cloned().collect();
+
+    /// the regular branches plus half steer either way for each straight one
+    pub static ref GROUND_ANALOG_CONTROL_BRANCHES: Vec<BrickControllerState> = {
+        let mut branches = GROUND_CONTROL_BRANCHES.clone();
+        for controller in GROUND_CONTROL_BRANCHES.iter().filter(|controller| controller.steer == Steer::Straight) {
+            for &steer in &[-0.5, 0.5] {
+                branches.push(BrickControllerState {
+                    steer: Steer::Analog(steer),
+                    ..controller.clone()
+                });
+            }
+        }
+        branches
+    };
+
+    pub static ref GROUND_ANALOG_NO_BOOST_CONTROL_BRANCHES: Vec<BrickControllerState> =
+        GROUND_ANALOG_CONTROL_BRANCHES.iter().filter(|controller| !controller.boost).cloned().collect();
 }
 
 const TICKS_PER_STEP: i32 = 1;
 const EXPLODED_STEP_DURATION: f32 = TICKS_PER_STEP as f32 * TICK;
 
+/// how long we follow the ball after a hit to see if it goes in
+const SHOT_SECONDS: f32 = 3.0;
+
+/// how long the live bot can spend on a single search before it takes a partial plan
+const PLAN_TIME_BUDGET: Duration = Duration::from_millis(100);
+
 /// wrapper around hybrid_a_star for convenience and some extra smarts. meant to be used by the
 /// live bot or bot simulation only, as it configures the serch paramters to favor speed over
 /// accuracy/optimality.
 // TODO maybe we should take the entire gamestate instead. we also need a history component, ie BotState
-pub fn plan<H: HeuristicModel>(
+pub fn plan<H: HeuristicModel, E: Evaluator>(
     model: &mut H,
     player: &PlayerState,
     ball_trajectory: &[BallState],
+    arena: &Arena,
     initial_ball_trajectory_index: usize,
     desired: &DesiredContact,
+    evaluator: &E,
     cost_to_strive_for: f32,
     _last_plan: Option<&Plan>,
 ) -> PlanResult {
@@ -141,6 +167,11 @@ pub fn plan<H: HeuristicModel>(
     // speed over optimality
     config.scale_heuristic = 10.0;
     config.max_iterations = 500;
+    // TODO add (2.0, 32.0 * TICK) once the 32-tick tables are built from 33-tick recordings
+    config.step_schedule = vec![(0.5, 16.0 * TICK), (0.0, 8.0 * TICK)];
+    // we boost a lot, so don't let a path that's used it all up crowd out one that saved some
+    config.cell.boost_size = Some(25.0);
+    config.deadline = Some(Instant::now() + PLAN_TIME_BUDGET);
 
     // if we have a perfectly good plan, we can use it as benchmark of when to stop looking
     // further, since if we get a worse plan now we'll ignore it.
@@ -155,16 +186,18 @@ pub fn plan<H: HeuristicModel>(
         model,
         player,
         ball_trajectory,
+        arena,
         initial_ball_trajectory_index,
         desired,
+        evaluator,
         cost_to_strive_for,
         &config,
     );
 
     match explode_plan(&plan_result.plan) {
         Ok(exploded) => plan_result.plan = exploded,
-        Err(e) => {
-            eprintln!("Exploding plan failed: {}", e);
+        Err(_) => {
+            plan_result.search_stats.prediction_failures += 1;
             plan_result.plan = None;
         }
     };
@@ -173,7 +206,7 @@ pub fn plan<H: HeuristicModel>(
 }
 
 /// modifies the plan to use finer-grained steps
-pub fn explode_plan(plan: &Option<Plan>) -> Result<Option<Plan>, Box<dyn Error>> {
+pub fn explode_plan(plan: &Option<Plan>) -> Result<Option<Plan>, PredictError> {
     if let Some(ref plan) = plan {
         if plan.get(0).is_none() {
             return Ok(None);
@@ -243,14 +276,19 @@ pub fn explode_plan(plan: &Option<Plan>) -> Result<Option<Plan>, Box<dyn Error>>
 
 #[derive(Clone, Debug)]
 struct PlayerVertex {
+    /// time so far, plus the cost of any boost used
     cost_so_far: f32,
+    /// cost so far plus the heuristic cost
+    estimated_cost: f32,
+    time_so_far: f32,
     player: PlayerState,
+    cell: RoundedPlayerState,
     /// the controller state in previous step that lead to this player vertex
     prev_controller: BrickControllerState,
     ball_trajectory_index: usize,
     step_duration: f32,
+    /// usize::MAX for the starting vertex
     parent_index: usize,
-    parent_is_secondary: bool,
 }
 
 #[derive(Debug)]
@@ -258,7 +296,6 @@ struct SmallestCostHolder {
     estimated_cost: f32,
     cost_so_far: f32,
     index: usize,
-    is_secondary: bool,
 }
 
 impl PartialEq for SmallestCostHolder {
@@ -289,76 +326,57 @@ impl<'a> PartialOrd for SmallestCostHolder {
 }
 
 // XXX is the use of i16 here actually helping?
+/// a grid cell in the search, see CellConfig. dimensions left out of the cell are always 0
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 struct RoundedPlayerState {
     x: i16,
     y: i16,
     z: i16,
-    //vx: i16,
-    //vy: i16,
-    //vz: i16,
-    //roll: i16,
-    //pitch: i16,
+    speed: i16,
+    roll: i16,
+    pitch: i16,
     yaw: i16,
+    boost: i16,
 }
 
-type ParentsMap = IndexMap<RoundedPlayerState, (PlayerVertex, Option<PlayerVertex>), MyHasher>;
-
-pub fn hybrid_a_star<H: HeuristicModel>(
+pub fn hybrid_a_star<H: HeuristicModel, E: Evaluator>(
     model: &mut H,
     current: &PlayerState,
     ball_trajectory: &[BallState],
+    arena: &Arena,
     initial_ball_trajectory_index: usize,
     desired: &DesiredContact,
+    evaluator: &E,
     cost_to_strive_for: f32,
     config: &SearchConfig,
 ) -> PlanResult {
-    // TODO take this fn as an argument, so different actions can have different goal_reached evaluation functions
-    let is_ball_hit_towards_goal: Evaluator = |ball_trajectory: &[BallState],
-                                               player: &PlayerState,
-                                               next_vertex: &PlayerVertex,
-                                               controller: &BrickControllerState,
-                                               time_step: f32|
-     -> Option<(PlayerState, BallState, f32)> {
-        let index = ((next_vertex.cost_so_far - next_vertex.step_duration) / TICK).round() as usize;
-        if let Some((colliding_player, colliding_ball, collision_point, collision_time)) =
-            predict::player::get_collision(&ball_trajectory[index..], player, controller, time_step)
-        {
-            match predict::ball::calculate_hit(&colliding_ball, &colliding_player, &collision_point) {
-                Ok(next_ball) => {
-                    if predict::ball::trajectory_enters_soccar_goal(&next_ball) {
-                        // println!("collision point: {:?}, ball velocity: {:?}", collision_point, next_ball.velocity);
-                        Some((colliding_player, colliding_ball, collision_time))
-                    } else {
-                        None
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Error calculating ball hit: {}", e);
-                    None
-                }
-            }
-        } else {
-            None
-        }
-    };
-
+    let search_start = Instant::now();
+    let mut stats = SearchStats::default();
     let mut visualization_lines = vec![];
 
     #[allow(unused_mut)]
     let mut visualization_points = vec![];
 
     // we can't fly yet :(
-    if ball_trajectory[initial_ball_trajectory_index].position.z - BALL_COLLISION_RADIUS > CAR_DIMENSIONS.z + CAR_OFFSET.z {
-        return PlanResult::default();
+    let max_reach_z = current.hitbox.dimensions().z + current.hitbox.offset().z;
+    if evaluator.needs_ball()
+        && ball_trajectory[initial_ball_trajectory_index].position.z - BALL_COLLISION_RADIUS > max_reach_z
+    {
+        stats.termination = SearchTermination::Unreachable;
+        return PlanResult {
+            search_stats: stats,
+            ..PlanResult::default()
+        };
     }
 
     // sets up the model for this particular prediction. it can do some calculations upfront here
     // instead of over and over again for each prediction.
     model.configure(&desired, config.scale_heuristic);
-
     let mut to_see: BinaryHeap<SmallestCostHolder> = BinaryHeap::new();
-    let mut parents: ParentsMap = IndexMap::default();
+    // append-only, so that indexes into it stay valid for the whole search
+    let mut nodes: Vec<PlayerVertex> = vec![];
+    // the index of the best node found so far in each cell
+    let mut best_in_cell: HashMap<RoundedPlayerState, usize, MyHasher> = HashMap::default();
 
     // buffers to avoid re-allocating in a loop
     let mut new_vertices = vec![];
@@ -371,77 +389,93 @@ pub fn hybrid_a_star<H: HeuristicModel>(
     model
         .heuristic(&[current.clone()], &mut single_heuristic_cost[0..1])
         .expect("Heuristic failed initial!");
+    stats.heuristic_calls += 1;
 
-    to_see.push(SmallestCostHolder {
-        estimated_cost: single_heuristic_cost[0],
-        cost_so_far: 0.0,
-        index: 0,
-        is_secondary: false,
-    });
-
-    let start = PlayerVertex {
+    let start_cell = round_player_state(&current, config.step_duration, &config.cell);
+    nodes.push(PlayerVertex {
         player: current.clone(),
+        cell: start_cell,
         cost_so_far: 0.0,
+        estimated_cost: single_heuristic_cost[0],
+        time_so_far: 0.0,
         prev_controller: BrickControllerState::default(),
         ball_trajectory_index: initial_ball_trajectory_index,
         step_duration: 0.0,
         parent_index: usize::MAX,
-        parent_is_secondary: false,
-    };
+    });
+    best_in_cell.insert(start_cell, 0);
 
-    parents.insert(
-        round_player_state(&current, config.step_duration, current.velocity.norm()),
-        (start, None),
-    );
+    to_see.push(SmallestCostHolder {
+        estimated_cost: single_heuristic_cost[0],
+        cost_so_far: 0.0,
+        index: 0,
+    });
+    stats.pushes += 1;
+    stats.max_open_set_size = 1;
 
     let mut num_iterations = 0;
+    let mut termination = SearchTermination::Exhausted;
+
+    // the node closest to the goal going by the heuristic, as (heuristic cost, index)
+    let mut most_promising: Option<(f32, usize)> = None;
 
     while let Some(SmallestCostHolder {
-        estimated_cost: _,
+        estimated_cost,
         cost_so_far,
         index,
-        is_secondary,
-        ..
     }) = to_see.pop()
     {
         // avoid an infinite graph search
         if cost_so_far > config.max_cost {
-            //println!("short circuit, hit max cost!");
+            termination = SearchTermination::MaxCost;
             break;
         }
 
         // HACK avoid very large searches completely
         num_iterations += 1;
         if num_iterations > config.max_iterations {
-            //println!("short circuit, too many iterations!");
+            termination = SearchTermination::MaxIterations;
             break;
         }
 
-        // FIXME bring back 32-tick steps. we lost 32-tick steps in latest sample runs, which are only 16 steps long
-        let dur = config.step_duration;
-        // let dur = if estimated_cost - cost_so_far > 2.0 {
-        //     // if we're really far... yeah just make it super coarse to make it tractable, and the
-        //     // search config has no control over this for now
-        //     32.0 * TICK
-        // } else {
-        //     config.step_duration
-        // };
+        let past_deadline = match config.deadline {
+            Some(deadline) => Instant::now() >= deadline,
+            None => false,
+        };
+        if past_deadline {
+            let plan = most_promising.and_then(|(_, index)| partial_plan(&nodes, index));
+            stats.termination = SearchTermination::Deadline;
+            stats.cells = best_in_cell.len();
+            stats.duration = search_start.elapsed();
+            return PlanResult {
+                partial: plan.is_some(),
+                plan,
+                ball_trajectory: ball_trajectory.to_vec(),
+                visualization_lines,
+                visualization_points,
+                search_stats: stats,
+                ..PlanResult::default()
+            };
+        }
+
+        let heuristic_cost = estimated_cost - cost_so_far;
+        let more_promising = match most_promising {
+            Some((best_cost, _)) => heuristic_cost < best_cost,
+            None => true,
+        };
+        if index != 0 && more_promising {
+            most_promising = Some((heuristic_cost, index));
+        }
+
+        let dur = step_duration_for(heuristic_cost, config);
 
         let line_start;
         {
-            let (_, (v1, maybe_v2)) = parents
-                .get_index(index)
-                .expect("missing index in parents, shouldn't be possible");
-            let vertex = if is_secondary { maybe_v2.as_ref().unwrap() } else { v1 };
+            let vertex = &nodes[index];
             line_start = vertex.player.position;
 
             let mut parent_player;
-            if let Some((_, (parent_v1, maybe_parent_v2))) = parents.get_index(vertex.parent_index) {
-                let parent_vertex = if vertex.parent_is_secondary {
-                    maybe_parent_v2.as_ref().unwrap()
-                } else {
-                    parent_v1
-                };
+            if let Some(parent_vertex) = nodes.get(vertex.parent_index) {
                 parent_player = parent_vertex.player.clone();
             } else {
                 // no parent, this can only happen on first expansion. we need to construct a fake
@@ -454,23 +488,30 @@ pub fn hybrid_a_star<H: HeuristicModel>(
                 parent_player.position.z += 0.1;
             }
 
-            if let Some((player, ball, cost)) = player_goal_reached(
-                &vertex,
-                &parent_player,
-                ball_trajectory,
-                &vertex.prev_controller,
-                config.step_duration,
-                is_ball_hit_towards_goal,
-            ) {
-                let plan = reverse_path(&parents, index, is_secondary, &player, cost);
+            // the starting vertex has no step of its own
+            let vertex_step_duration = if vertex.parent_index == usize::MAX {
+                config.step_duration
+            } else {
+                vertex.step_duration
+            };
+
+            let step = SearchStep {
+                previous_player: &parent_player,
+                player: &vertex.player,
+                controller: &vertex.prev_controller,
+                duration: vertex_step_duration,
+                ball_trajectory_index: ((vertex.time_so_far - vertex.step_duration) / TICK).round() as usize,
+                target_ball_trajectory_index: vertex.ball_trajectory_index,
+            };
+
+            if let Some((player, ball, cost)) = evaluator.evaluate(&step, ball_trajectory, arena, &mut stats) {
+                let plan = reverse_path(&nodes, index, &player, cost);
 
                 let total_cost = plan.iter().map(|(_, _, cost)| cost).sum::<f32>();
-                // println!(
-                //     "omg reached! step size: {} | expansions: {} | cost: {}",
-                //     config.step_duration * 120.0,
-                //     visualization_lines.len(),
-                //     total_cost,
-                // );
+                stats.termination = SearchTermination::Reached;
+                stats.cells = best_in_cell.len();
+                stats.plan_cost = Some(total_cost);
+                stats.duration = search_start.elapsed();
                 return PlanResult {
                     plan: Some(plan),
                     planned_ball: Some(ball),
@@ -479,33 +520,37 @@ pub fn hybrid_a_star<H: HeuristicModel>(
                     ball_trajectory: ball_trajectory.to_vec(),
                     visualization_lines,
                     visualization_points,
+                    strategy: None, // caller sets it
+                    partial: false,
+                    search_stats: stats,
                 };
-            } else if coarse_collision(&vertex, &parent_player, &ball_trajectory[vertex.ball_trajectory_index]) {
+            } else if coarse_collision(&vertex.player, &parent_player, &ball_trajectory[vertex.ball_trajectory_index]) {
                 // if we hit the ball but we didn't reach the goal, we skip instead of expanding
                 // this vertex
-                let index = ((vertex.cost_so_far - vertex.step_duration) / TICK).round() as usize;
+                let index = ((vertex.time_so_far - vertex.step_duration) / TICK).round() as usize;
                 if predict::player::get_collision(
                     &ball_trajectory[index..],
                     &parent_player,
                     &vertex.prev_controller,
-                    config.step_duration,
+                    vertex_step_duration,
                 )
                 .is_some()
                 {
+                    stats.pruned += 1;
                     continue;
                 }
             }
 
-            // We may have inserted a node several times into the binary heap if we found
-            // a better way to access it. Ensure that we are currently dealing with the
-            // best path and discard the others.
-            //
-            // NOTE: this also achieves the same thing as checking if we are in the closed set
-            if cost_so_far > vertex.cost_so_far {
+            // a cheaper node has taken over this node's cell since it was pushed, so the other
+            // node is the one worth expanding. along with pushing the cheaper node, this stands in
+            // for the open set decreaseKey operation, and also for checking the closed set
+            if best_in_cell.get(&vertex.cell) != Some(&index) {
+                stats.duplicates += 1;
                 continue;
             }
 
-            expand_vertex(index, is_secondary, &vertex, &mut new_vertices, dur, config.custom_filter)
+            stats.expansions += 1;
+            stats.prediction_failures += expand_vertex(index, &vertex, &mut new_vertices, dur, arena, config);
         };
 
         new_players.clear();
@@ -514,24 +559,28 @@ pub fn hybrid_a_star<H: HeuristicModel>(
         if let Some(first_new_vertex) = new_vertices.get(0) {
             let index = first_new_vertex.ball_trajectory_index;
             let wtf: &[PlayerState] = &new_players;
-            set_heuristic_costs(model, wtf, &mut cur_heuristic_costs, &ball_trajectory, index);
+            set_heuristic_costs(model, evaluator, wtf, &mut cur_heuristic_costs, &ball_trajectory, index);
             set_heuristic_costs(
                 model,
+                evaluator,
                 &new_players,
                 &mut prev_heuristic_costs,
                 &ball_trajectory,
                 index.wrapping_sub(1),
             );
-            set_heuristic_costs(model, &new_players, &mut next_heuristic_costs, &ball_trajectory, index + 1);
+            set_heuristic_costs(
+                model,
+                evaluator,
+                &new_players,
+                &mut next_heuristic_costs,
+                &ball_trajectory,
+                index + 1,
+            );
+            stats.heuristic_calls += 3;
         }
 
         for (i, mut new_vertex) in new_vertices.drain(0..).enumerate() {
-            let new_vertex_rounded = round_player_state(&new_vertex.player, dur, new_vertex.player.velocity.norm());
-            let new_cost_so_far = new_vertex.cost_so_far;
-            let new_index;
-            let mut new_is_secondary = false;
             let line_end = new_vertex.player.position;
-            let mut new_estimated_cost = 0.0;
 
             let cur_diff = (cur_heuristic_costs[i] - TICK * new_vertex.ball_trajectory_index as f32).abs();
             let prev_diff = (prev_heuristic_costs[i] - TICK * (new_vertex.ball_trajectory_index as f32 - 1.0)).abs();
@@ -545,127 +594,43 @@ pub fn hybrid_a_star<H: HeuristicModel>(
                 new_vertex.ball_trajectory_index += 1;
                 next_heuristic_costs[i]
             };
-
-            match parents.entry(new_vertex_rounded) {
-                Vacant(e) => {
-                    new_index = e.index();
-                    new_estimated_cost = new_cost_so_far + heuristic_cost;
-                    e.insert((new_vertex, None));
+            new_vertex.estimated_cost = new_vertex.cost_so_far + heuristic_cost;
+
+            // same cell expansion. due to the consistent nature of the heuristic, a new vertex that
+            // is closer to the goal will have a higher cost-so-far than its parent, even if in the
+            // same cell. so this is the workaround from Karl Kurzer's masters thesis: when
+            // expanding within the parent's cell, we compare the whole estimate instead. since
+            // nodes are never replaced, we can do this any number of times in a row
+            let same_cell_as_parent = nodes[new_vertex.parent_index].cell == new_vertex.cell;
+            let is_better = match best_in_cell.get(&new_vertex.cell) {
+                None => true,
+                Some(&best_index) => {
+                    let best = &nodes[best_index];
+                    new_vertex.cost_so_far < best.cost_so_far
+                        || (same_cell_as_parent && new_vertex.estimated_cost < best.estimated_cost)
                 }
-                Occupied(mut e) => {
-                    new_index = e.index();
-                    new_is_secondary = true;
-                    let insertable;
-                    match e.get() {
-                        (existing_vertex, None) => {
-                            // basically just like the vacant case
-                            new_estimated_cost = new_cost_so_far + heuristic_cost;
-                            // TODO-perf avoid the clone here. nll? worst-case, can use mem::replace with an enum
-                            insertable = Some((existing_vertex.clone(), Some(new_vertex)));
-                        }
-                        (existing_vertex, Some(existing_secondary_vertex)) => {
-                            // FIXME we seem to only be checking/replacing the secondary vertex..
-                            // what about the primary?
-                            let mut new_cost_is_lower = existing_secondary_vertex.cost_so_far > new_vertex.cost_so_far;
-                            if new_cost_is_lower {
-                                // turns out that the index invalidation is a problem in general
-                                // with the IndexMap approach when replacing occupied entries: we
-                                // end up with some nonsensical plan jumps otherwise. instead,
-                                // let's limit replacements to siblings. this does mean we may miss
-                                // out on the best path, but at least doesn't give us corrupted
-                                // paths! if this causes us to lose too many good paths, we'll need
-                                // to reconsider the use of the IndexMap
-                                if existing_secondary_vertex.parent_index != new_vertex.parent_index {
-                                    continue;
-                                }
-
-                                new_estimated_cost = new_cost_so_far + heuristic_cost;
-                            } else if e.index() == new_vertex.parent_index
-                                || new_vertex.parent_index == existing_secondary_vertex.parent_index
-                            {
-                                // same cell expansion. due to the consistent nature of the heuristic, a new
-                                // vertex that is closer to the goal will have a higher cost-so-far than its
-                                // parent, even if in the same cell. so this is the workaround from
-                                // Karl Kurzer's masters thesis.
-                                //
-                                // extending that idea since we are using indexes in the indexmap for
-                                // performance, which makes things more complicated. replacing any value in
-                                // the indexmap actually invalidates the index that any smallest cost
-                                // holders may be referring to. in the case of a child overwriting its
-                                // parent, it actually leads to a cycle, which makes reversing the path an
-                                // infinite loop!
-                                //
-                                // the secondary workaround then is to have a .. secondary value in each
-                                // cell. thus we allow same-cell expansion only 1 time. expanding the child
-                                // a second time and getting to the same grid cell will result in ignoring
-                                // the grandchild. we could generalize this to n expansions and store an
-                                // array instead, but it doesn't seem necessary.
-
-                                // if we're expanding in the same cell as the parent, but. the
-                                // parent was already a secondary, we want to ignore as there's no
-                                // spot to put ourselves in
-                                if e.index() == new_vertex.parent_index && new_vertex.parent_is_secondary {
-                                    continue;
-                                }
-
-                                // turns out that the index invalidation is a problem in general,
-                                // since we end up with some nonsensical plan jumps otherwise,
-                                // though rarely.  instead, let's limit this extra logic to
-                                // same-cell expansion.  however, this prunes paths that may be the
-                                // path we want
-                                if e.index() != new_vertex.parent_index {
-                                    continue;
-                                }
-
-                                // now check if we are better than the secondary using the
-                                // whole estimate, given this scenario is mostly made for children
-                                // of the same parent, and thus pretty similar if not exactly the
-                                // same cost so far. we don't want a tie-breaker like Karl's
-                                // version had since we are not comparing against a parent
-                                // directly, but a sibling!
-                                new_estimated_cost = new_cost_so_far + heuristic_cost;
-
-                                set_heuristic_costs(
-                                    model,
-                                    &[existing_secondary_vertex.player.clone()],
-                                    &mut single_heuristic_cost,
-                                    &ball_trajectory,
-                                    existing_secondary_vertex.ball_trajectory_index,
-                                );
-                                let existing_secondary_estimated_cost =
-                                    existing_secondary_vertex.cost_so_far + single_heuristic_cost[0];
-
-                                if new_estimated_cost < existing_secondary_estimated_cost {
-                                    new_cost_is_lower = true;
-                                }
-                            }
-
-                            if new_cost_is_lower {
-                                // TODO-perf avoid the clone here. can use mem::replace with an enum
-                                insertable = Some((existing_vertex.clone(), Some(new_vertex)));
-                            } else {
-                                //visualization_points.push((
-                                //    Point3::new(line_end.x, line_end.y, line_end.z),
-                                //    Point3::new(0.4, 0.0, 0.0),
-                                //));
-                                visualization_lines.push((
-                                    Point3::new(line_start.x, line_start.y, line_start.z),
-                                    Point3::new(
-                                        0.1 * (line_end.x - line_start.x) + line_start.x,
-                                        0.1 * (line_end.y - line_start.y) + line_start.y,
-                                        0.1 * (line_end.z - line_start.z) + line_start.z,
-                                    ),
-                                    Point3::new(0.4, 0.0, 0.0),
-                                ));
-                                continue;
-                            }
-                        }
-                    }
+            };
 
-                    if let Some(v) = insertable {
-                        e.insert(v);
-                    }
-                }
+            if !is_better {
+                stats.pruned += 1;
+                //visualization_points.push((
+                //    Point3::new(line_end.x, line_end.y, line_end.z),
+                //    Point3::new(0.4, 0.0, 0.0),
+                //));
+                visualization_lines.push((
+                    Point3::new(line_start.x, line_start.y, line_start.z),
+                    Point3::new(
+                        0.1 * (line_end.x - line_start.x) + line_start.x,
+                        0.1 * (line_end.y - line_start.y) + line_start.y,
+                        0.1 * (line_end.z - line_start.z) + line_start.z,
+                    ),
+                    Point3::new(0.4, 0.0, 0.0),
+                ));
+                continue;
+            }
+
+            if same_cell_as_parent {
+                stats.same_cell_expansions += 1;
             }
 
             //visualization_points.push((
@@ -682,37 +647,45 @@ pub fn hybrid_a_star<H: HeuristicModel>(
                 Point3::new(0.6, 0.6, 0.6),
             ));
 
-            // rustc is forcing us to initialize this every time because it doesn't understand data
-            // flow, so let's manually make sure we aren't in the initial state which is never right
-            assert!(new_estimated_cost != 0.0);
-
-            // NOTE this insertion into to_see is a replacement for the open set decreaseKey
-            // operation. there is also a check for multiple insertions earlier.
+            let new_index = nodes.len();
+            best_in_cell.insert(new_vertex.cell, new_index);
             to_see.push(SmallestCostHolder {
-                estimated_cost: new_estimated_cost,
-                cost_so_far: new_cost_so_far,
+                estimated_cost: new_vertex.estimated_cost,
+                cost_so_far: new_vertex.cost_so_far,
                 index: new_index,
-                is_secondary: new_is_secondary,
             });
+            nodes.push(new_vertex);
+            stats.pushes += 1;
+            stats.max_open_set_size = stats.max_open_set_size.max(to_see.len());
         }
     }
 
-    // println!(
-    //     "omg failed! step size: {} | expansions: {} | left: {}",
-    //     config.step_duration * 120.0,
-    //     visualization_lines.len(),
-    //     to_see.len()
-    // );
-
+    stats.termination = termination;
+    stats.cells = best_in_cell.len();
+    stats.duration = search_start.elapsed();
     PlanResult {
         visualization_lines,
         visualization_points,
+        search_stats: stats,
         ..PlanResult::default()
     }
 }
+/// long steps while far from the goal keep long approaches tractable, while short steps close to
+/// it keep contact precise
+fn step_duration_for(heuristic_cost: f32, config: &SearchConfig) -> f32 {
+    // the schedule is in seconds, like the unscaled heuristic
+    let heuristic_cost = heuristic_cost / config.scale_heuristic;
+    config
+        .step_schedule
+        .iter()
+        .find(|(min_heuristic_cost, _)| heuristic_cost >= *min_heuristic_cost)
+        .map(|(_, step_duration)| *step_duration)
+        .unwrap_or(config.step_duration)
+}
 
-fn set_heuristic_costs<H: HeuristicModel>(
+fn set_heuristic_costs<H: HeuristicModel, E: Evaluator>(
     model: &mut H,
+    evaluator: &E,
     new_players: &[PlayerState],
     costs: &mut Vec<f32>,
     ball_trajectory: &[BallState],
@@ -722,9 +695,8 @@ fn set_heuristic_costs<H: HeuristicModel>(
         costs.push(0.0)
     }
 
-    let goal = Vector3::new(0.0, BACK_WALL_DISTANCE, BALL_COLLISION_RADIUS); // FIXME don't hard-code
     if let Some(ball) = ball_trajectory.get(ball_trajectory_index) {
-        model.ball_configure(&ball, &goal); // FIXME adjust this for ball velocity
+        evaluator.configure_heuristic(model, ball);
     } else {
         // just make it a high cost as the ball doesn't exist in this offset as far as we know
         for cost in costs.iter_mut() {
@@ -738,44 +710,230 @@ fn set_heuristic_costs<H: HeuristicModel>(
         .expect("Heuristic failed!");
 }
 
-fn coarse_collision(candidate_vertex: &PlayerVertex, previous_player: &PlayerState, ball: &BallState) -> bool {
+fn coarse_collision(candidate_player: &PlayerState, previous_player: &PlayerState, ball: &BallState) -> bool {
     // the bounding box size includes the car dimensions because we use the center of the car's
     // position to create the line for the coarse collision check
-    let size = BALL_COLLISION_RADIUS + CAR_DIMENSIONS.norm() / 2.0;
+    let size = BALL_COLLISION_RADIUS + candidate_player.hitbox.dimensions().norm() / 2.0;
 
     let coarse_box = BoundingBox::new(&ball.position, size);
     // XXX note if using large time steps, this will be especially  inaccurate as we assume prev to
     // current is a straight line but it may be curved. this is offset by the fact that we use the
     // maximium car dimension to extend the bounding box though, so we may have fewer false
     // negatives than otherwise
-    line_collides_bounding_box(
-        &coarse_box,
-        previous_player.hitbox_center(),
-        candidate_vertex.player.hitbox_center(),
-    )
+    line_collides_bounding_box(&coarse_box, previous_player.hitbox_center(), candidate_player.hitbox_center())
+}
+
+/// a single step of the search, from the parent vertex's player to a candidate one
+pub struct SearchStep<'a> {
+    pub previous_player: &'a PlayerState,
+    pub player: &'a PlayerState,
+    /// what takes us from previous_player to player
+    pub controller: &'a BrickControllerState,
+    pub duration: f32,
+    /// the ball at the start of the step
+    pub ball_trajectory_index: usize,
+    /// the ball we're planning to hit, ie initial_ball_trajectory_index
+    pub target_ball_trajectory_index: usize,
 }
 
-type Evaluator =
-    fn(&[BallState], &PlayerState, &PlayerVertex, &BrickControllerState, f32) -> Option<(PlayerState, BallState, f32)>; // consider using Fn trait + generics to make this inlinable
+/// decides when the search has reached its goal, so that different actions can search for
+/// different things
+pub trait Evaluator {
+    /// if the step reaches the goal, returns the player and ball at that moment, plus the time
+    /// into the step that it happens at
+    fn evaluate(
+        &self,
+        step: &SearchStep,
+        ball_trajectory: &[BallState],
+        arena: &Arena,
+        stats: &mut SearchStats,
+    ) -> Option<(PlayerState, BallState, f32)>;
+
+    /// whether the goal involves touching the ball, so we can give up early if it's out of reach
+    fn needs_ball(&self) -> bool {
+        true
+    }
 
-fn player_goal_reached(
-    candidate_vertex: &PlayerVertex,
-    previous_player: &PlayerState,
-    ball_trajectory: &[BallState],
-    controller: &BrickControllerState,
-    time_step: f32,
-    evaluator: Evaluator,
-) -> Option<(PlayerState, BallState, f32)> {
-    let coarse_collision = coarse_collision(
-        candidate_vertex,
-        previous_player,
-        &ball_trajectory[candidate_vertex.ball_trajectory_index],
-    );
-    if !coarse_collision {
+    /// points the heuristic at this goal, given the ball at the time we'd get there. defaults to
+    /// hitting the ball towards the opponent goal, which is always at +y since we plan in the
+    /// team-normalized frame
+    fn configure_heuristic<H: HeuristicModel>(&self, model: &mut H, ball: &BallState) {
+        let goal = Vector3::new(0.0, BACK_WALL_DISTANCE, BALL_COLLISION_RADIUS);
+        model.ball_configure(ball, &goal); // FIXME adjust this for ball velocity
+    }
+}
+
+/// the ball is hit so that it goes into the opponent's goal
+pub struct HitIntoGoal;
+
+/// the ball is hit so that it heads towards the target point, passing within tolerance of it
+pub struct HitTowards {
+    pub target: Vector3<f32>,
+    pub tolerance: f32,
+}
+
+/// any touch of the ball at all
+pub struct TouchBall;
+
+/// we get to a position, facing the given heading, regardless of the ball
+pub struct ReachPose {
+    pub position: Vector3<f32>,
+    pub heading: Unit<Vector3<f32>>,
+    pub tolerance: f32,
+    /// dot product of our heading and the desired heading must be at least this
+    pub min_dot: f32,
+}
+
+/// tuple of (player, ball, collision point, time into the step) where the step touches the ball
+fn step_collision(step: &SearchStep, ball_trajectory: &[BallState]) -> Option<(PlayerState, BallState, Vector3<f32>, f32)> {
+    if !coarse_collision(
+        step.player,
+        step.previous_player,
+        &ball_trajectory[step.target_ball_trajectory_index],
+    ) {
         return None;
-    };
+    }
+
+    predict::player::get_collision(
+        &ball_trajectory[step.ball_trajectory_index..],
+        step.previous_player,
+        step.controller,
+        step.duration,
+    )
+}
+
+/// like step_collision, but with the ball state right after the hit instead of the collision point
+fn step_hit(
+    step: &SearchStep,
+    ball_trajectory: &[BallState],
+    arena: &Arena,
+    stats: &mut SearchStats,
+) -> Option<(PlayerState, BallState, BallState, f32)> {
+    let (colliding_player, colliding_ball, collision_point, collision_time) = step_collision(step, ball_trajectory)?;
+    match predict::ball::calculate_hit(&colliding_ball, &colliding_player, &collision_point, arena) {
+        Ok(next_ball) => Some((colliding_player, colliding_ball, next_ball, collision_time)),
+        Err(_) => {
+            stats.prediction_failures += 1;
+            None
+        }
+    }
+}
 
-    evaluator(ball_trajectory, previous_player, candidate_vertex, controller, time_step)
+impl Evaluator for HitIntoGoal {
+    fn evaluate(
+        &self,
+        step: &SearchStep,
+        ball_trajectory: &[BallState],
+        arena: &Arena,
+        stats: &mut SearchStats,
+    ) -> Option<(PlayerState, BallState, f32)> {
+        let (player, ball, next_ball, time) = step_hit(step, ball_trajectory, arena, stats)?;
+        stats.shot_checks += 1;
+        if predict::ball::trajectory_enters_goal(&next_ball, SHOT_SECONDS, arena).is_some() {
+            Some((player, ball, time))
+        } else {
+            None
+        }
+    }
+}
+
+impl HitTowards {
+    /// whether the ball is headed to pass within tolerance of the target
+    // NOTE only looks at the direction the ball leaves in, ignoring bounces and gravity
+    fn heads_towards(&self, ball: &BallState) -> bool {
+        let direction = Vector3::new(ball.velocity.x, ball.velocity.y, 0.0);
+        let to_target = self.target - ball.position;
+        let to_target = Vector3::new(to_target.x, to_target.y, 0.0);
+        if direction.norm() == 0.0 || na::Matrix::dot(&direction, &to_target) <= 0.0 {
+            return false;
+        }
+
+        let miss_distance = direction.normalize().cross(&to_target).norm();
+        miss_distance <= self.tolerance
+    }
+}
+
+impl Evaluator for HitTowards {
+    fn evaluate(
+        &self,
+        step: &SearchStep,
+        ball_trajectory: &[BallState],
+        arena: &Arena,
+        stats: &mut SearchStats,
+    ) -> Option<(PlayerState, BallState, f32)> {
+        let (player, ball, next_ball, time) = step_hit(step, ball_trajectory, arena, stats)?;
+        if self.heads_towards(&next_ball) {
+            Some((player, ball, time))
+        } else {
+            None
+        }
+    }
+
+    fn configure_heuristic<H: HeuristicModel>(&self, model: &mut H, ball: &BallState) {
+        model.ball_configure(ball, &self.target);
+    }
+}
+
+impl Evaluator for TouchBall {
+    fn evaluate(
+        &self,
+        step: &SearchStep,
+        ball_trajectory: &[BallState],
+        _arena: &Arena,
+        _stats: &mut SearchStats,
+    ) -> Option<(PlayerState, BallState, f32)> {
+        let (player, ball, _, time) = step_collision(step, ball_trajectory)?;
+        Some((player, ball, time))
+    }
+}
+
+impl Evaluator for ReachPose {
+    fn evaluate(
+        &self,
+        step: &SearchStep,
+        ball_trajectory: &[BallState],
+        _arena: &Arena,
+        _stats: &mut SearchStats,
+    ) -> Option<(PlayerState, BallState, f32)> {
+        if na::Matrix::dot(&step.player.heading(), &self.heading) < self.min_dot {
+            return None;
+        }
+
+        // we may pass the position in the middle of the step, so check the whole line
+        let start = step.previous_player.position;
+        let line = step.player.position - start;
+        let along = if line.norm() == 0.0 {
+            0.0
+        } else {
+            na::clamp(
+                na::Matrix::dot(&(self.position - start), &line) / line.norm_squared(),
+                0.0,
+                1.0,
+            )
+        };
+        if (start + along * line - self.position).norm() > self.tolerance {
+            return None;
+        }
+
+        let index = step.ball_trajectory_index + (step.duration / TICK).round() as usize;
+        let ball = ball_trajectory.get(index).or_else(|| ball_trajectory.last())?;
+        Some((step.player.clone(), ball.clone(), step.duration))
+    }
+
+    fn needs_ball(&self) -> bool {
+        false
+    }
+
+    fn configure_heuristic<H: HeuristicModel>(&self, model: &mut H, _ball: &BallState) {
+        // the heuristic expects a contact point at the front of the car, not the car's centre
+        let heading = self.heading.into_inner();
+        let desired = DesiredContact {
+            position: self.position + (Hitbox::default().dimensions().x / 2.0) * heading,
+            heading,
+        };
+        let scale = model.scale();
+        model.configure(&desired, scale);
+    }
 }
 
 // NOTE this gets you a plan which consists of tuples of (player, prev_controller, cost). to
@@ -783,20 +941,11 @@ fn player_goal_reached(
 // plan, which will correspond to the correspond to the controller action required now in ordert
 // to reach the next player state
 #[allow(clippy::needless_collect)] // it's actually needed as rev does not work after unfold
-fn reverse_path(
-    parents: &ParentsMap,
-    initial_index: usize,
-    initial_is_secondary: bool,
-    initial_player: &PlayerState,
-    initial_cost: f32,
-) -> Plan {
-    let path = itertools::unfold((initial_index, initial_is_secondary), |vals| {
-        let index = (*vals).0;
-        let is_secondary = (*vals).1;
-        parents.get_index(index).map(|(_rounded, (v1, maybe_v2))| {
-            let vertex = if is_secondary { maybe_v2.as_ref().unwrap() } else { v1 };
-            (*vals).0 = vertex.parent_index;
-            (*vals).1 = vertex.parent_is_secondary;
+fn reverse_path(nodes: &[PlayerVertex], initial_index: usize, initial_player: &PlayerState, initial_cost: f32) -> Plan {
+    let path = itertools::unfold(initial_index, |next_index| {
+        let index = *next_index;
+        nodes.get(index).map(|vertex| {
+            *next_index = vertex.parent_index;
             let player = if index == initial_index {
                 initial_player.clone()
             } else {
@@ -815,6 +964,12 @@ fn reverse_path(
     path.into_iter().rev().collect()
 }
 
+/// the path to the given vertex, stopping short of the goal
+fn partial_plan(nodes: &[PlayerVertex], index: usize) -> Option<Plan> {
+    let vertex = nodes.get(index)?;
+    Some(reverse_path(nodes, index, &vertex.player, vertex.step_duration))
+}
+
 /// not the opponent's goal. this is the goal for our a* search!
 #[derive(Copy, Clone, Debug)]
 pub struct Goal {
@@ -906,10 +1061,11 @@ pub fn line_collides_bounding_box(bounding_box: &BoundingBox, start: Vector3<f32
     ray_collides_bounding_box(&bounding_box, start, end) && ray_collides_bounding_box(&bounding_box, end, start)
 }
 
-fn round_player_state(player: &PlayerState, step_duration: f32, speed: f32) -> RoundedPlayerState {
+fn round_player_state(player: &PlayerState, step_duration: f32, cell: &CellConfig) -> RoundedPlayerState {
     // we're using the rounded speed to determine the grid size. we want a good bit of tolerance for
     // this, if we relax the rounded velocity equality check. or some other logic that will ensure
     // same grid for different player states that we want to match
+    let speed = player.velocity.norm();
     let rounding_factor = 1.0; // TODO tune. for both correctness AND speed!
     let mut rounded_speed = (speed / rounding_factor).round();
     if rounded_speed == 0.0 {
@@ -917,42 +1073,56 @@ fn round_player_state(player: &PlayerState, step_duration: f32, speed: f32) -> R
     }
     let rounded_speed = rounded_speed * rounding_factor;
 
-    let grid_size = step_duration * rounded_speed;
-    //let velocity_margin = 250.0; // TODO tune
-    let (_roll, _pitch, yaw) = player.rotation.euler_angles();
+    let grid_size = cell.position_scale * step_duration * rounded_speed;
+    // XXX is this the best way to round a rotation matrix? do discontinuities in euler angles
+    // cause problems here?
+    // TODO use angular velocity to determine margin of rounding.
+    let (roll, pitch, yaw) = player.rotation.euler_angles();
+    let roll_pitch_size = cell.roll_pitch_buckets.map(|buckets| 2.0 * PI / f32::from(buckets));
 
     RoundedPlayerState {
         // TODO we could have individual grid sizes for x/y/z based on vx/vy/vz. not sure it's
         // worth it.
         x: (grid_size * (player.position.x / grid_size).round()) as i16,
         y: (grid_size * (player.position.y / grid_size).round()) as i16,
-        z: 0i16, // FIXME // (grid_size * (player.position.z / grid_size).round()) as i16,
-
-        //   // XXX including velocity in the search space might just be too much. but let's give it
-        //   // a shot sometime later.
-        //   vx: 0, // TODO // (player.velocity.x / velocity_margin).floor() as i16,
-        //   vy: 0, // TODO // (player.velocity.y / velocity_margin).floor() as i16,
-        //   vz: 0, // TODO // (player.velocity.z / velocity_margin).floor() as i16,
-
-        //   // XXX is this the best way to round a rotation matrix? do discontinuities in euler angles
-        //   // cause problems here?
-        //   // TODO use angular velocity to determine margin of rounding.
-        //   // XXX including rotation in search space also seems like too much for now
-        //   roll: 0, //(roll * 10.0).floor() as i16,
-        //   pitch: 0, //(pitch * 10.0).floor() as i16,
-        yaw: (yaw / (PI / 8.0)).round() as i16, // round to nearest pi/4 angle
+        z: bucket(player.position.z, cell.z_size),
+        speed: bucket(speed, cell.speed_size),
+        roll: bucket(roll, roll_pitch_size),
+        pitch: bucket(pitch, roll_pitch_size),
+        yaw: bucket(yaw, Some(2.0 * PI / f32::from(cell.yaw_buckets))),
+        boost: match cell.boost_size {
+            Some(size) => (player.boost / size).ceil() as i16,
+            None => 0,
+        },
+    }
+}
+
+fn bucket(value: f32, size: Option<f32>) -> i16 {
+    match size {
+        Some(size) => (value / size).round() as i16,
+        None => 0,
     }
 }
 
-fn control_branches(player: &PlayerState) -> &'static Vec<BrickControllerState> {
+/// boost branches are left out unless we have enough boost for the whole step, since the driving
+/// model assumes we boost throughout
+fn control_branches(player: &PlayerState, step_duration: f32, analog: bool) -> &'static Vec<BrickControllerState> {
+    let can_boost = player.boost >= BOOST_DEPLETION_RATE * step_duration;
+    let ground_branches: &'static Vec<BrickControllerState> = match (can_boost, analog) {
+        (true, false) => &GROUND_CONTROL_BRANCHES,
+        (false, false) => &GROUND_NO_BOOST_CONTROL_BRANCHES,
+        (true, true) => &GROUND_ANALOG_CONTROL_BRANCHES,
+        (false, true) => &GROUND_ANALOG_NO_BOOST_CONTROL_BRANCHES,
+    };
+
     match predict::player::find_prediction_category(&player) {
-        // TODO if we ran out of boost, then need a boost-less version
-        predict::player::PredictionCategory::Ground => &GROUND_CONTROL_BRANCHES
+        predict::player::PredictionCategory::Ground => ground_branches,
+        // TODO air control branches. we predict air as ground for now, so use the same branches
+        predict::player::PredictionCategory::Air => ground_branches,
         //PredictionCategory::Ground2 => TODO,
         //PredictionCategory::Wall => TODO,
         //PredictionCategory::Ceiling => TODO,
         //PredictionCategory::CurveWall => TODO,
-        //PredictionCategory::Air => TODO,
     }
 }
 
@@ -960,55 +1130,67 @@ fn control_branches(player: &PlayerState) -> &'static Vec<BrickControllerState>
 // we get prediction for driving on the curves/walls
 // TODO well we should model the goal area as drivable too
 const BOUNDS_MARGIN: f32 = 200.0;
-fn out_of_bounds(player: &PlayerState) -> bool {
-    let pos = player.position;
-    pos.x.abs() > SIDE_CURVE_DISTANCE + BOUNDS_MARGIN || pos.y.abs() > BACK_CURVE_DISTANCE + BOUNDS_MARGIN
+fn out_of_bounds(player: &PlayerState, arena: &Arena) -> bool {
+    arena.beyond_curves(&player.position, BOUNDS_MARGIN)
 }
 
 fn expand_vertex(
     index: usize,
-    is_secondary: bool,
     vertex: &PlayerVertex,
     new_vertices: &mut Vec<PlayerVertex>,
     step_duration: f32,
-    custom_filter: Option<fn(&PlayerState) -> bool>,
-) {
-    let iterator = control_branches(&vertex.player)
+    arena: &Arena,
+    config: &SearchConfig,
+) -> u32 {
+    let mut failures = 0;
+    let iterator = control_branches(&vertex.player, step_duration, config.analog_branches)
         .iter()
-        .map(|controller: &BrickControllerState| -> Result<PlayerVertex, String> {
-            let next_player = predict::player::next_player_state(&vertex.player, &controller, step_duration);
-            if next_player.is_err() {
-                // print to stderr now since we're swallowing these errors right after this
-                eprintln!("Warning: failed to expand vertex: {}", next_player.as_ref().unwrap_err());
-            }
+        .map(|controller: &BrickControllerState| -> Result<PlayerVertex, PredictError> {
+            let next_player = predict::player::next_player_state(&vertex.player, &controller, step_duration)?;
+            let boost_used = vertex.player.boost - next_player.boost;
+            let cell = round_player_state(&next_player, step_duration, &config.cell);
+            let cost_so_far = vertex.cost_so_far + step_duration + config.boost_cost * boost_used;
 
             Ok(PlayerVertex {
-                player: next_player?,
-                cost_so_far: vertex.cost_so_far + step_duration,
+                player: next_player,
+                cell,
+                cost_so_far,
+                // the caller adds the heuristic cost
+                estimated_cost: cost_so_far,
+                time_so_far: vertex.time_so_far + step_duration,
                 prev_controller: controller.clone(),
                 ball_trajectory_index: vertex.ball_trajectory_index,
                 step_duration,
                 parent_index: index,
-                parent_is_secondary: is_secondary,
             })
         })
-        .filter_map(Result::ok)
+        .filter_map(|new_vertex| match new_vertex {
+            Ok(new_vertex) => Some(new_vertex),
+            Err(_) => {
+                // these are usually states we have no driving model samples near, so they are
+                // common and not worth reporting individually
+                failures += 1;
+                None
+            }
+        })
         .filter(|new_vertex| {
-            if let Some(filter_func) = custom_filter {
+            if let Some(filter_func) = config.custom_filter {
                 filter_func(&new_vertex.player)
             } else {
                 // if parent (ie vertex) is already out of bounds, allow going out of bounds since we need
                 // to be able to move back in if we start planning from out of bounds (eg player inside
                 // goal). we're  only allowing if we're getting *less* out of bounds than before
-                !out_of_bounds(&new_vertex.player) || out_of_bounds(&vertex.player)
+                !out_of_bounds(&new_vertex.player, arena) || out_of_bounds(&vertex.player, arena)
             }
         });
     new_vertices.extend(iterator);
+    failures
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use predict::arena::SOCCAR_ARENA;
     use std::f32::consts::PI;
 
     fn get_model() -> impl HeuristicModel {
@@ -1041,6 +1223,9 @@ mod tests {
             angular_velocity: resting_velocity(),
             rotation: resting_rotation(),
             team: Team::Blue,
+            boost: MAX_BOOST,
+            hitbox: Hitbox::Octane,
+            ..PlayerState::default()
         }
     }
 
@@ -1055,6 +1240,109 @@ mod tests {
         }
     }
 
+    #[test]
+    fn boost_branches_need_enough_boost_for_the_step() {
+        let step_duration = 16.0 * TICK;
+        let mut player = resting_player_state();
+        assert!(control_branches(&player, step_duration, false).iter().any(|c| c.boost));
+
+        player.boost = 0.5 * BOOST_DEPLETION_RATE * step_duration;
+        assert!(!control_branches(&player, step_duration, false).iter().any(|c| c.boost));
+        assert!(!control_branches(&player, step_duration, true).iter().any(|c| c.boost));
+    }
+
+    #[test]
+    fn reverse_path_follows_parents_through_the_arena() {
+        let vertex = |x: f32, parent_index: usize| {
+            let mut player = resting_player_state();
+            player.position.x = x;
+            PlayerVertex {
+                cost_so_far: 0.0,
+                estimated_cost: 0.0,
+                time_so_far: 0.0,
+                cell: round_player_state(&player, TICK, &CellConfig::default()),
+                player,
+                prev_controller: BrickControllerState::default(),
+                ball_trajectory_index: 0,
+                step_duration: TICK,
+                parent_index,
+            }
+        };
+        // a later node in the same cell as an earlier one doesn't affect the earlier node's path
+        let nodes = vec![vertex(0.0, usize::MAX), vertex(1.0, 0), vertex(2.0, 0), vertex(3.0, 2)];
+
+        let plan = partial_plan(&nodes, 3).expect("no plan");
+        let xs: Vec<f32> = plan.iter().map(|(player, _, _)| player.position.x).collect();
+        assert_eq!(xs, vec![0.0, 2.0, 3.0]);
+        assert!(partial_plan(&nodes, 4).is_none());
+    }
+
+    #[test]
+    fn speed_only_splits_cells_when_configured() {
+        let mut slow = resting_player_state();
+        slow.velocity = Vector3::new(0.0, 500.0, 0.0);
+        let mut fast = slow.clone();
+        fast.velocity = Vector3::new(0.0, 2000.0, 0.0);
+        // same spot for both grid sizes
+        fast.position.y = 1000.0;
+        slow.position.y = 1000.0;
+        let step_duration = 16.0 * TICK;
+
+        let cell = CellConfig::default();
+        let slow_cell = round_player_state(&slow, step_duration, &cell);
+        let fast_cell = round_player_state(&fast, step_duration, &cell);
+        assert_eq!(slow_cell.speed, fast_cell.speed);
+
+        let cell = CellConfig {
+            speed_size: Some(250.0),
+            ..CellConfig::default()
+        };
+        let slow_cell = round_player_state(&slow, step_duration, &cell);
+        let fast_cell = round_player_state(&fast, step_duration, &cell);
+        assert_ne!(slow_cell.speed, fast_cell.speed);
+    }
+
+    #[test]
+    fn boost_only_splits_cells_when_configured() {
+        let full = resting_player_state();
+        let mut empty = full.clone();
+        empty.boost = 0.0;
+        let step_duration = 16.0 * TICK;
+
+        let cell = CellConfig::default();
+        assert_eq!(
+            round_player_state(&full, step_duration, &cell),
+            round_player_state(&empty, step_duration, &cell)
+        );
+
+        let cell = CellConfig {
+            boost_size: Some(25.0),
+            ..CellConfig::default()
+        };
+        assert_ne!(
+            round_player_state(&full, step_duration, &cell),
+            round_player_state(&empty, step_duration, &cell)
+        );
+    }
+
+    #[test]
+    fn coarser_steps_further_from_the_goal() {
+        let mut config = SearchConfig {
+            step_schedule: vec![(2.0, 32.0 * TICK), (0.5, 16.0 * TICK), (0.0, 8.0 * TICK)],
+            ..SearchConfig::default()
+        };
+        assert_eq!(step_duration_for(3.0, &config), 32.0 * TICK);
+        assert_eq!(step_duration_for(1.0, &config), 16.0 * TICK);
+        assert_eq!(step_duration_for(0.1, &config), 8.0 * TICK);
+
+        // the schedule is in unscaled seconds
+        config.scale_heuristic = 10.0;
+        assert_eq!(step_duration_for(10.0, &config), 16.0 * TICK);
+
+        config.step_schedule = vec![];
+        assert_eq!(step_duration_for(3.0, &config), config.step_duration);
+    }
+
     #[test]
     fn line_doesnt_collide_even_though_ray_does() {
         let slop = 1.0;
@@ -1148,7 +1436,17 @@ mod tests {
         let desired = test_desired_contact();
         let mut model = get_model();
         let config = SearchConfig::default();
-        let PlanResult { plan, .. } = hybrid_a_star(&mut model, &current, &[test_ball()], 0, &desired, 0.0, &config);
+        let PlanResult { plan, .. } = hybrid_a_star(
+            &mut model,
+            &current,
+            &[test_ball()],
+            &SOCCAR_ARENA,
+            0,
+            &desired,
+            &TouchBall,
+            0.0,
+            &config,
+        );
         //assert!(plan.is_some());
         if plan.is_some() {
             count += 1
@@ -1172,7 +1470,17 @@ mod tests {
             let desired = test_desired_contact();
             let mut model = get_model();
             let config = SearchConfig::default();
-            let PlanResult { plan, .. } = hybrid_a_star(&mut model, &current, &[test_ball()], 0, &desired, 0.0, &config);
+            let PlanResult { plan, .. } = hybrid_a_star(
+                &mut model,
+                &current,
+                &[test_ball()],
+                &SOCCAR_ARENA,
+                0,
+                &desired,
+                &TouchBall,
+                0.0,
+                &config,
+            );
             //assert!(plan.is_some());
             if plan.is_some() {
                 count += 1
@@ -1252,6 +1560,131 @@ mod tests {
     //     assert!(failures.is_empty());
     // }
 
+    #[test]
+    fn reach_pose_checks_the_whole_step() {
+        let previous_player = resting_player_state();
+        let mut player = resting_player_state();
+        player.position.y += 300.0;
+        let step = SearchStep {
+            previous_player: &previous_player,
+            player: &player,
+            controller: &BrickControllerState::default(),
+            duration: 16.0 * TICK,
+            ball_trajectory_index: 0,
+            target_ball_trajectory_index: 0,
+        };
+        let mut evaluator = ReachPose {
+            position: previous_player.position + Vector3::new(20.0, 150.0, 0.0),
+            heading: Unit::new_normalize(player.heading()),
+            tolerance: 50.0,
+            min_dot: 0.9,
+        };
+        let mut stats = SearchStats::default();
+        assert!(evaluator.evaluate(&step, &[test_ball()], &SOCCAR_ARENA, &mut stats).is_some());
+
+        evaluator.heading = -evaluator.heading;
+        assert!(evaluator.evaluate(&step, &[test_ball()], &SOCCAR_ARENA, &mut stats).is_none());
+
+        evaluator.heading = -evaluator.heading;
+        evaluator.position.x += 100.0;
+        assert!(evaluator.evaluate(&step, &[test_ball()], &SOCCAR_ARENA, &mut stats).is_none());
+    }
+
+    #[test]
+    fn hit_towards_checks_the_miss_distance() {
+        let mut ball = test_ball();
+        ball.position = Vector3::new(0.0, 0.0, BALL_COLLISION_RADIUS);
+        ball.velocity = Vector3::new(0.0, 1000.0, 0.0);
+        let mut evaluator = HitTowards {
+            target: Vector3::new(100.0, 3000.0, 0.0),
+            tolerance: 150.0,
+        };
+        assert!(evaluator.heads_towards(&ball));
+
+        evaluator.tolerance = 50.0;
+        assert!(!evaluator.heads_towards(&ball));
+
+        // a ball heading away never gets there, no matter how well lined up it is
+        evaluator.tolerance = 150.0;
+        ball.velocity = -ball.velocity;
+        assert!(!evaluator.heads_towards(&ball));
+
+        ball.velocity = Vector3::new(0.0, 0.0, 0.0);
+        assert!(!evaluator.heads_towards(&ball));
+    }
+
+    #[test]
+    fn ball_evaluators_need_a_touch() {
+        let previous_player = resting_player_state();
+        let mut player = resting_player_state();
+        player.position.y += 100.0;
+        let step = SearchStep {
+            previous_player: &previous_player,
+            player: &player,
+            controller: &BrickControllerState::default(),
+            duration: 16.0 * TICK,
+            ball_trajectory_index: 0,
+            target_ball_trajectory_index: 0,
+        };
+        let mut ball = test_ball();
+        ball.position = Vector3::new(0.0, 3000.0, BALL_COLLISION_RADIUS);
+        let ball_trajectory = [ball];
+        let mut stats = SearchStats::default();
+
+        assert!(TouchBall
+            .evaluate(&step, &ball_trajectory, &SOCCAR_ARENA, &mut stats)
+            .is_none());
+        assert!(HitIntoGoal
+            .evaluate(&step, &ball_trajectory, &SOCCAR_ARENA, &mut stats)
+            .is_none());
+        let hit_towards = HitTowards {
+            target: Vector3::new(0.0, BACK_WALL_DISTANCE, 0.0),
+            tolerance: 1000.0,
+        };
+        assert!(hit_towards
+            .evaluate(&step, &ball_trajectory, &SOCCAR_ARENA, &mut stats)
+            .is_none());
+        assert_eq!(stats.shot_checks, 0);
+    }
+
+    #[test]
+    fn evaluators_choose_the_heuristic_target() {
+        let mut ball = test_ball();
+        ball.position = Vector3::new(0.0, 0.0, BALL_COLLISION_RADIUS);
+        let mut behind = resting_player_state();
+        behind.position.y = -1000.0;
+        let mut in_front = resting_player_state();
+        in_front.position.y = 1000.0;
+        let players = [behind, in_front.clone()];
+        let mut costs = [0.0; 2];
+        let mut model = get_model();
+
+        // hitting into the opponent goal at +y means getting behind the ball
+        HitIntoGoal.configure_heuristic(&mut model, &ball);
+        model.unscaled_heuristic(&players, &mut costs).unwrap();
+        assert!(costs[0] < costs[1]);
+
+        let hit_towards = HitTowards {
+            target: Vector3::new(0.0, -BACK_WALL_DISTANCE, 0.0),
+            tolerance: 100.0,
+        };
+        hit_towards.configure_heuristic(&mut model, &ball);
+        model.unscaled_heuristic(&players, &mut costs).unwrap();
+        assert!(costs[1] < costs[0]);
+
+        // reaching a pose ignores the ball entirely
+        let reach_pose = ReachPose {
+            position: in_front.position + 50.0 * in_front.heading(),
+            heading: Unit::new_normalize(in_front.heading()),
+            tolerance: 100.0,
+            min_dot: 0.7,
+        };
+        reach_pose.configure_heuristic(&mut model, &ball);
+        model.unscaled_heuristic(&players, &mut costs).unwrap();
+        assert!(costs[1] < 0.05, "cost just short of the pose: {}", costs[1]);
+        assert!(costs[1] < costs[0]);
+    }
+
     #[test]
     fn unreachable() {
         let distance = -10_000;
@@ -1260,7 +1693,17 @@ mod tests {
         let desired = test_desired_contact();
         let mut model = get_model();
         let config = SearchConfig::default();
-        let PlanResult { plan, .. } = hybrid_a_star(&mut model, &current, &[test_ball()], 0, &desired, 0.0, &config);
+        let PlanResult { plan, .. } = hybrid_a_star(
+            &mut model,
+            &current,
+            &[test_ball()],
+            &SOCCAR_ARENA,
+            0,
+            &desired,
+            &TouchBall,
+            0.0,
+            &config,
+        );
         assert!(plan.is_none());
     }
 }
@@ -1,32 +1,93 @@
 use crate::HeuristicModel; // TODO as _;
 use na::{self, Rotation3, Unit, Vector3};
 use plan;
-use predict::{self, player::PredictPlayer};
+use predict::{self, arena::Arena, player::PredictPlayer};
 use rlbot;
 use state::*;
 use std::collections::VecDeque;
 use std::f32::consts::PI;
 use std::time::Instant;
+use strategy;
 
-// TODO we need to also include our current (ie previously used) strategy state as an input here,
-// and logic for expiring it if it's no longer applicable.
-fn what_do(_game: &GameState) -> Action {
-    Action::Shoot // TODO
-}
+/// how far from the ball we stay when shadowing, towards our own goal
+const SHADOW_DISTANCE: f32 = 1500.0;
+
+/// how far ahead in the ball trajectory we pick the ball to shadow
+const SHADOW_LOOKAHEAD: f32 = 0.5;
+
+/// how fast we assume the ball goes after we shoot it
+const SHOT_SPEED: f32 = 3000.0;
 
+/// how many points across and up the goal mouth we consider shooting at
+const SHOT_TARGET_COLUMNS: usize = 5;
+const SHOT_TARGET_ROWS: usize = 3;
+
+/// how often we check along the shot whether an opponent can get to the ball first
+const SHOT_SAMPLE_SECONDS: f32 = 0.1;
+
+/// the point in the goal mouth that's hardest for the opponents to get in front of, going by how
+/// much later than the ball they can get to any point along the shot. the shot is from where the
+/// ball is now. expects a team-normalized game, where the opponent goal is always towards +y
 pub fn opponent_goal_shoot_at(game: &GameState) -> Vector3<f32> {
-    // TODO calculate which part of the goal is hardest for the opponent to reach
-    match game.player.team {
-        // FIXME check if we have this right...
-        Team::Blue => Vector3::new(0.0, BACK_WALL_DISTANCE, GOAL_Z / 2.0),
-        Team::Orange => Vector3::new(0.0, -BACK_WALL_DISTANCE, GOAL_Z / 2.0),
+    let centre = Vector3::new(0.0, BACK_WALL_DISTANCE, GOAL_Z / 2.0);
+    if game.opponents().next().is_none() {
+        return centre;
     }
+
+    // keep the whole ball inside the goal mouth
+    let max_x = GOAL_X - BALL_COLLISION_RADIUS;
+    let min_z = BALL_COLLISION_RADIUS;
+    let max_z = GOAL_Z - BALL_COLLISION_RADIUS;
+
+    let mut best_target = centre;
+    let mut best_margin = std::f32::MIN;
+    for column in 0..SHOT_TARGET_COLUMNS {
+        for row in 0..SHOT_TARGET_ROWS {
+            let x = -max_x + 2.0 * max_x * column as f32 / (SHOT_TARGET_COLUMNS - 1) as f32;
+            let z = min_z + (max_z - min_z) * row as f32 / (SHOT_TARGET_ROWS - 1) as f32;
+            let target = Vector3::new(x, BACK_WALL_DISTANCE, z);
+
+            let margin = shot_margin(game, &game.ball.position, &target);
+            if margin > best_margin {
+                best_margin = margin;
+                best_target = target;
+            }
+        }
+    }
+
+    best_target
+}
+
+/// the least time the ball has on the opponents anywhere along a straight shot from start to
+/// target. negative when an opponent can get in front of it
+fn shot_margin(game: &GameState, start: &Vector3<f32>, target: &Vector3<f32>) -> f32 {
+    let shot = target - start;
+    let shot_seconds = shot.norm() / SHOT_SPEED;
+    let samples = (shot_seconds / SHOT_SAMPLE_SECONDS).ceil() as usize;
+
+    (0..=samples)
+        .map(|i| (i as f32 * SHOT_SAMPLE_SECONDS).min(shot_seconds))
+        .flat_map(|seconds| {
+            let ball_position = start + shot * (seconds / shot_seconds.max(std::f32::EPSILON));
+            game.opponents()
+                .map(move |(_, opponent)| opponent_intercept_time(opponent, &ball_position) - seconds)
+        })
+        .fold(std::f32::MAX, f32::min)
+}
+
+/// a deliberately optimistic guess for how soon the opponent can get to the ball at the given
+/// position: driving straight there at top speed
+// TODO use the opponent's velocity and heading, and account for them jumping or flying up to it
+fn opponent_intercept_time(opponent: &PlayerState, ball_position: &Vector3<f32>) -> f32 {
+    let reach = BALL_COLLISION_RADIUS + opponent.hitbox.dimensions().x / 2.0;
+    let distance = (ball_position - opponent.position).norm();
+    (distance - reach).max(0.0) / MAX_BOOST_SPEED
 }
 
 /// guess best point on ball to hit, get the heading at that point
 pub fn simple_desired_contact(ball: &BallState, desired_ball_position: &Vector3<f32>) -> DesiredContact {
     let desired_vector = Unit::new_normalize(desired_ball_position - ball.position);
-    let desired_velocity = 3000.0 * desired_vector.into_inner();
+    let desired_velocity = SHOT_SPEED * desired_vector.into_inner();
     let velocity_delta = desired_velocity - ball.velocity;
 
     // this is pretty crude, doesn't even consider that the ball will undergo gravity after the
@@ -82,40 +143,206 @@ fn reachable_contact_and_time<H: HeuristicModel>(
     (desired_contact, reachable_time)
 }
 
-fn shoot<H: HeuristicModel>(model: &mut H, game: &GameState, bot: &mut BotState) -> PlanResult {
+fn shoot<H: HeuristicModel>(model: &mut H, game: &GameState, arena: &Arena, bot: &mut BotState) -> PlanResult {
     let desired_ball_position: Vector3<f32> = opponent_goal_shoot_at(&game);
     let last_plan = None;
     // FIXME check if last plan is still valid before using this
     // let last_plan
-    //     if bot.last_action.is_some() && bot.last_action.as_ref().unwrap() == &Action::Shoot {
+    //     if bot.strategy.as_ref().map(|strategy| &strategy.action) == Some(&Action::Shoot) {
     //         bot.plan.as_ref()
     //     } else {
     //         None
     //     };
-    let result = hit_ball(model, game, bot, &desired_ball_position, last_plan);
-    bot.last_action = Some(Action::Shoot);
+
+    //let start = Instant::now();
+    let ball_trajectory = predict::ball::ball_trajectory(&game.ball, 10.0, arena);
+    //println!("#############################");
+    //println!("BALL DURATION: {:?}", start.elapsed());
+    //println!("#############################");
+
+    hit_ball(
+        model,
+        game,
+        arena,
+        bot,
+        &ball_trajectory,
+        &desired_ball_position,
+        &plan::HitIntoGoal,
+        last_plan,
+    )
+}
+
+fn save<H: HeuristicModel>(
+    model: &mut H,
+    game: &GameState,
+    arena: &Arena,
+    ball_trajectory: &[BallState],
+    own_goal_entry: Option<usize>,
+    bot: &mut BotState,
+) -> PlanResult {
+    // we have to get there before it goes in, otherwise it's too late anyways
+    let entry_index = match own_goal_entry {
+        Some(entry_index) if entry_index > 0 => entry_index,
+        _ => return PlanResult::default(),
+    };
+
+    // any hit that sends it upfield will do, no need to be precise
+    let clear_target = Vector3::new(0.0, arena.back_wall_distance, 0.0);
+    let evaluator = plan::HitTowards {
+        target: clear_target,
+        tolerance: arena.side_wall_distance,
+    };
+
+    hit_ball(
+        model,
+        game,
+        arena,
+        bot,
+        &ball_trajectory[..entry_index],
+        &clear_target,
+        &evaluator,
+        None,
+    )
+}
+
+/// the position between the ball and our goal that we shadow from, facing the ball. expects a
+/// team-normalized game
+fn shadow_pose(ball: &BallState, arena: &Arena) -> (Vector3<f32>, Unit<Vector3<f32>>) {
+    let own_goal = Vector3::new(0.0, -arena.back_wall_distance, 0.0);
+    let mut to_goal = own_goal - ball.position;
+    to_goal.z = 0.0;
+
+    let mut position = ball.position + SHADOW_DISTANCE.min(to_goal.norm()) * to_goal.normalize();
+    // stay on the part of the field we can drive on
+    position.x = na::clamp(position.x, -arena.side_curve_distance, arena.side_curve_distance);
+    position.y = na::clamp(position.y, -arena.back_curve_distance, arena.back_curve_distance);
+    position.z = Hitbox::default().resting_z();
+
+    let mut to_ball = ball.position - position;
+    to_ball.z = 0.0;
+    let heading = if to_ball.norm() == 0.0 {
+        Vector3::y_axis()
+    } else {
+        Unit::new_normalize(to_ball)
+    };
+
+    (position, heading)
+}
+
+fn shadow<H: HeuristicModel>(model: &mut H, game: &GameState, arena: &Arena, bot: &mut BotState) -> PlanResult {
+    let ball_trajectory = predict::ball::ball_trajectory(&game.ball, 10.0, arena);
+
+    let index = ((SHADOW_LOOKAHEAD / TICK).round() as usize).min(ball_trajectory.len() - 1);
+    let (position, heading) = shadow_pose(&ball_trajectory[index], arena);
+    let evaluator = plan::ReachPose {
+        position,
+        heading,
+        tolerance: 100.0,
+        min_dot: 0.7,
+    };
+    let desired = DesiredContact {
+        position,
+        heading: heading.into_inner(),
+    };
+
+    let mut result = plan::plan(
+        model,
+        // FIXME additional lag should be added for brick's planning calculation lag
+        &game.player.lag_compensated_player(&bot.controller_history, LAG_FRAMES),
+        &ball_trajectory,
+        arena,
+        index,
+        &desired,
+        &evaluator,
+        SHADOW_LOOKAHEAD,
+        None,
+    );
+    result.source_frame = game.frame;
     result
 }
 
-fn hit_ball<H: HeuristicModel>(
+/// the standard kickoff spawns, as seen from the blue side. the diagonal and off-centre spawns
+/// come in a left and right variant, mirrored in x
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KickoffSpawn {
+    Diagonal,
+    OffCentre,
+    BackCentre,
+}
+
+impl KickoffSpawn {
+    /// goes by the direction from the centre spot rather than the closest spawn, so that we keep
+    /// getting the same answer as we drive in towards the ball. expects a team-normalized position
+    pub fn from_position(position: &Vector3<f32>) -> KickoffSpawn {
+        let slope = position.x.abs() / position.y.abs().max(1.0);
+        if slope > 0.4 {
+            KickoffSpawn::Diagonal
+        } else if slope > 0.03 {
+            KickoffSpawn::OffCentre
+        } else {
+            KickoffSpawn::BackCentre
+        }
+    }
+
+    /// the blue spawn position, the right variant where there are two
+    pub fn position(self) -> Vector3<f32> {
+        let z = Hitbox::default().resting_z();
+        match self {
+            KickoffSpawn::Diagonal => Vector3::new(2048.0, -2560.0, z),
+            KickoffSpawn::OffCentre => Vector3::new(256.0, -3840.0, z),
+            KickoffSpawn::BackCentre => Vector3::new(0.0, -4608.0, z),
+        }
+    }
+}
+
+/// where each kickoff routine sends the ball, and how close to that it has to go. expects a
+/// team-normalized player
+fn kickoff_target(spawn: KickoffSpawn, player: &PlayerState) -> (Vector3<f32>, f32) {
+    let side = if player.position.x < 0.0 { -1.0 } else { 1.0 };
+    match spawn {
+        // we have the longest run up, so there's time to line up a proper shot
+        KickoffSpawn::BackCentre => (Vector3::new(0.0, BACK_WALL_DISTANCE, GOAL_Z / 2.0), GOAL_X / 2.0),
+        // we come in at a slight angle anyways, so cross it towards the far post
+        KickoffSpawn::OffCentre => (
+            Vector3::new(-side * GOAL_X / 2.0, BACK_WALL_DISTANCE, GOAL_Z / 2.0),
+            GOAL_X / 2.0,
+        ),
+        // no time to turn, so carry on along our line of approach and just get it upfield past
+        // the opponent
+        KickoffSpawn::Diagonal => {
+            let mut target = -(BACK_WALL_DISTANCE / player.position.y.abs().max(1.0)) * player.position;
+            target.z = 0.0;
+            (target, SIDE_WALL_DISTANCE)
+        }
+    }
+}
+
+fn kickoff<H: HeuristicModel>(model: &mut H, game: &GameState, arena: &Arena, bot: &mut BotState) -> PlanResult {
+    // the ball just sits there until someone touches it
+    let ball_trajectory = predict::ball::ball_trajectory(&game.ball, 5.0, arena);
+
+    let spawn = KickoffSpawn::from_position(&game.player.position);
+    let (target, tolerance) = kickoff_target(spawn, &game.player);
+    let evaluator = plan::HitTowards { target, tolerance };
+
+    hit_ball(model, game, arena, bot, &ball_trajectory, &target, &evaluator, None)
+}
+
+fn hit_ball<H: HeuristicModel, E: plan::Evaluator>(
     model: &mut H,
     game: &GameState,
+    arena: &Arena,
     bot: &BotState,
+    ball_trajectory: &[BallState],
     desired_ball_position: &Vector3<f32>,
+    evaluator: &E,
     last_plan: Option<&Plan>,
 ) -> PlanResult {
-    //let start = Instant::now();
-    let ball_trajectory = predict::ball::ball_trajectory(&game.ball, 10.0);
-    //println!("#############################");
-    //println!("BALL DURATION: {:?}", start.elapsed());
-    //println!("#############################");
-    //let start = Instant::now();
-
     let (desired_contact, time) = reachable_contact_and_time(
         model,
         // FIXME additional lag should be added for brick's planning calculation lag
         &game.player.lag_compensated_player(&bot.controller_history, LAG_FRAMES),
-        &ball_trajectory,
+        ball_trajectory,
         &desired_ball_position,
     );
 
@@ -132,9 +359,11 @@ fn hit_ball<H: HeuristicModel>(
         model,
         // FIXME additional lag should be added for brick's planning calculation lag
         &game.player.lag_compensated_player(&bot.controller_history, LAG_FRAMES),
-        &ball_trajectory,
+        ball_trajectory,
+        arena,
         initial_ball_trajectory_index,
         &desired_contact,
+        evaluator,
         time,
         last_plan,
     );
@@ -145,7 +374,7 @@ fn hit_ball<H: HeuristicModel>(
 
 fn non_admissable_estimated_time<H: HeuristicModel>(model: &mut H, current: &PlayerState, ball: &BallState) -> f32 {
     // unreachable, we can't fly
-    if ball.position.z - BALL_COLLISION_RADIUS > CAR_DIMENSIONS.z + CAR_OFFSET.z {
+    if ball.position.z - BALL_COLLISION_RADIUS > current.hitbox.dimensions().z + current.hitbox.offset().z {
         return std::f32::MAX;
     }
 
@@ -156,17 +385,41 @@ fn non_admissable_estimated_time<H: HeuristicModel>(model: &mut H, current: &Pla
     unsafe { *single_heuristic_cost.get_unchecked(0) }
 }
 
-// TODO
-//fn shadow(game: &GameState) -> PlayerState {
-//}
-
 /// main entrypoint for bot to figure out what to do given the current state
-// TODO we need to also include our current (ie previously used) strategy state as an input here,
-// and logic for expiring it if it's no longer applicable.
 pub fn play<H: HeuristicModel>(model: &mut H, game: &GameState, bot: &mut BotState) -> PlanResult {
-    match what_do(game) {
-        Action::Shoot => shoot(model, game, bot),
+    // everything from here on is written as if we're blue, attacking towards +y
+    let team = game.player.team;
+    let game = &game.normalized(team);
+
+    // already logged when loading it failed, and there's nothing we can plan without it
+    let arena = match predict::arena::arena(game.arena) {
+        Ok(arena) => arena,
+        Err(_) => return PlanResult::default(),
+    };
+
+    // what's going into our own goal decides both the action and the save, so only work it out once
+    let save_trajectory = predict::ball::ball_trajectory(&game.ball, strategy::SAVE_HORIZON, &arena);
+    let own_goal_entry = strategy::own_goal_entry_index(&save_trajectory, &arena);
+    let mut result = match strategy::what_do(game, own_goal_entry, bot) {
+        Action::Shoot => shoot(model, game, &arena, bot),
+        Action::Shadow => shadow(model, game, &arena, bot),
+        Action::Save => save(model, game, &arena, &save_trajectory, own_goal_entry, bot),
+        Action::Kickoff => kickoff(model, game, &arena, bot),
+    };
+
+    // NOTE we don't check directly whether the last plan is still valid. failing to replan stands
+    // in for that, see strategy::MAX_FAILED_PLANS
+    if let Some(ref mut strategy) = bot.strategy {
+        if result.plan.is_some() && !result.partial {
+            strategy.failed_plans = 0;
+        } else {
+            strategy.failed_plans += 1;
+        }
     }
+
+    // the bot state we were given is a copy, so pass the strategy back with the result
+    result.strategy = bot.strategy.clone();
+    result.denormalized(team)
 }
 
 pub fn closest_plan_index(given_player: &PlayerState, plan: &[PlanStep]) -> usize {
@@ -236,7 +489,8 @@ pub fn next_input(player: &PlayerState, bot: &mut BotState) -> rlbot::Controller
     // fallback
     let mut input = rlbot::ControllerState::default();
     input.throttle = 1.0;
-    if player.position.z > 150.0 && (player.position.z as i32 % 2) == 0 {
+    // we can only plan on the ground, so jump off if we're stuck on a wall or the ceiling
+    if player.on_ground && player.position.z > 150.0 {
         input.jump = true;
     }
     input
@@ -278,3 +532,74 @@ fn pd_adjust(input: &mut rlbot::ControllerState, errors: &VecDeque<f32>) {
         input.throttle -= THROTTLE_FACTOR * diff;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kickoff_spawn_stays_the_same_on_the_way_to_the_ball() {
+        for &spawn in &[KickoffSpawn::Diagonal, KickoffSpawn::OffCentre, KickoffSpawn::BackCentre] {
+            let position = spawn.position();
+            let left = Vector3::new(-position.x, position.y, position.z);
+            assert_eq!(KickoffSpawn::from_position(&position), spawn);
+            assert_eq!(KickoffSpawn::from_position(&left), spawn);
+            assert_eq!(KickoffSpawn::from_position(&(0.5 * position)), spawn);
+        }
+    }
+
+    #[test]
+    fn shoots_away_from_the_goalie() {
+        let mut game = GameState {
+            ball: BallState {
+                position: Vector3::new(0.0, 2000.0, BALL_COLLISION_RADIUS),
+                ..BallState::default()
+            },
+            ..GameState::default()
+        };
+        game.players = vec![game.player.clone()];
+        assert_eq!(
+            opponent_goal_shoot_at(&game),
+            Vector3::new(0.0, BACK_WALL_DISTANCE, GOAL_Z / 2.0)
+        );
+
+        let goalie = PlayerState {
+            position: Vector3::new(-600.0, BACK_WALL_DISTANCE - 200.0, 17.0),
+            team: Team::Orange,
+            ..PlayerState::default()
+        };
+        game.players.push(goalie);
+        assert!(opponent_goal_shoot_at(&game).x > 0.0);
+    }
+
+    #[test]
+    fn shadow_plan_ends_near_the_shadow_pose() {
+        let mut game = GameState {
+            ball: BallState {
+                position: Vector3::new(1000.0, 1000.0, BALL_COLLISION_RADIUS),
+                ..BallState::default()
+            },
+            ..GameState::default()
+        };
+        let arena = &predict::arena::SOCCAR_ARENA;
+        let (position, heading) = shadow_pose(&game.ball, arena);
+        game.player = PlayerState {
+            position: position - 1000.0 * heading.into_inner(),
+            rotation: na::UnitQuaternion::rotation_between(&Vector3::new(-1.0, 0.0, 0.0), &heading).unwrap(),
+            team: Team::Blue,
+            boost: MAX_BOOST,
+            ..PlayerState::default()
+        };
+
+        let mut model = ::heuristic::BasicHeuristic::default();
+        let result = shadow(&mut model, &game, arena, &mut BotState::default());
+        let plan = result.plan.expect("Expected a shadow plan");
+        assert!(!result.partial);
+
+        // the ball barely moves while we get there, so the pose we planned for is still the same
+        let (last_player, _, _) = plan.last().unwrap();
+        let distance = (last_player.position - position).norm();
+        assert!(distance < 150.0, "ended {} away from the shadow pose", distance);
+        assert!(na::Matrix::dot(&last_player.heading(), &heading) > 0.7);
+    }
+}
@@ -0,0 +1,234 @@
+//! picks which action to take. once picked, we stick with an action for a while instead of
+//! deciding from scratch every planning cycle, which would have us flip-flopping between actions
+//! (and their plans) from one cycle to the next.
+
+use predict::arena::Arena;
+use state::*;
+
+/// how close to the end of regulation time we switch to end-of-game behaviour
+const LAST_SECONDS: f32 = 30.0;
+
+/// how far ahead we look for the ball going into our goal
+pub(crate) const SAVE_HORIZON: f32 = 3.0;
+
+/// once picked, we keep an action for at least this long, unless it expires
+const COMMITMENT_SECONDS: f32 = 1.0;
+
+/// after the commitment window, another action has to score this much higher to take over
+const SWITCH_MARGIN: f32 = 0.1;
+
+/// we give up on an action if planning for it keeps failing. this stands in for expiring when the
+/// plan becomes invalid: we replan every cycle anyways, so a plan that no longer works shows up as
+/// failing to find a new one
+const MAX_FAILED_PLANS: u32 = 10;
+
+/// we give up on an action once it scores this much lower than when we picked it, since whatever
+/// made it look good has changed
+const MAX_SCORE_DROP: f32 = 0.25;
+
+/// touches closer together than this are the same touch. the touch time is a float that goes
+/// through the game and our own conversions, so it may not come back exactly the same
+const SAME_TOUCH_SECONDS: f32 = TICK / 2.0;
+
+const ACTIONS: [Action; 4] = [Action::Shoot, Action::Shadow, Action::Save, Action::Kickoff];
+
+/// picks the action for this planning cycle, updating the strategy kept in the bot state. expects
+/// a team-normalized game, and the ball's own goal entry index for this cycle, see
+/// own_goal_entry_index
+pub fn what_do(game: &GameState, own_goal_entry: Option<usize>, bot: &mut BotState) -> Action {
+    let (best_action, best_score) = ACTIONS
+        .iter()
+        .map(|action| (action, entry_score(game, own_goal_entry, action)))
+        .fold((&Action::Shoot, f32::MIN), |best, candidate| {
+            if candidate.1 > best.1 {
+                candidate
+            } else {
+                best
+            }
+        });
+
+    let keep = match bot.strategy {
+        Some(ref strategy) => should_keep(
+            strategy,
+            game,
+            entry_score(game, own_goal_entry, &strategy.action),
+            best_score,
+        ),
+        None => false,
+    };
+
+    if !keep {
+        bot.strategy = Some(Strategy {
+            action: best_action.clone(),
+            entry_score: best_score,
+            started_frame: game.frame,
+            last_touch: game.last_touch.clone(),
+            failed_plans: 0,
+        });
+    }
+
+    bot.strategy
+        .as_ref()
+        .map(|strategy| strategy.action.clone())
+        .unwrap_or(Action::Shoot)
+}
+
+/// how good an action looks right now, higher is better. zero means it doesn't apply at all
+fn entry_score(game: &GameState, own_goal_entry: Option<usize>, action: &Action) -> f32 {
+    let match_info = &game.match_info;
+    let goal_difference = match_info.goal_difference(game.player.team);
+    let ending_soon = match_info.is_ending_soon(LAST_SECONDS);
+    let kickoff = game.is_kickoff();
+
+    match action {
+        Action::Kickoff if kickoff => 1.0,
+        Action::Kickoff => 0.0,
+        _ if kickoff => 0.0,
+        // behind with the clock running out: all in on attack, which is what shooting is
+        Action::Shoot if ending_soon && goal_difference < 0 => 0.8,
+        Action::Shoot => 0.5,
+        Action::Save if own_goal_entry.is_some() => 0.9,
+        Action::Save => 0.0,
+        // ahead with the clock running out: stay goal side and let them come to us
+        Action::Shadow if ending_soon && goal_difference > 0 => 0.8,
+        // we're on the wrong side of the ball, so get back first
+        Action::Shadow if game.player.position.y > game.ball.position.y + BALL_COLLISION_RADIUS => 0.7,
+        Action::Shadow => 0.0,
+    }
+}
+
+fn should_keep(strategy: &Strategy, game: &GameState, current_score: f32, best_score: f32) -> bool {
+    if expired(strategy, game, current_score) {
+        return false;
+    }
+
+    let committed_seconds = game.frame.wrapping_sub(strategy.started_frame) as f32 * TICK;
+    committed_seconds < COMMITMENT_SECONDS || best_score <= current_score + SWITCH_MARGIN
+}
+
+fn expired(strategy: &Strategy, game: &GameState, current_score: f32) -> bool {
+    current_score <= 0.0
+        || current_score < strategy.entry_score - MAX_SCORE_DROP
+        || strategy.failed_plans >= MAX_FAILED_PLANS
+        || touched_by_other(strategy, game)
+}
+
+/// whether someone other than us touched the ball since we picked the action
+fn touched_by_other(strategy: &Strategy, game: &GameState) -> bool {
+    match (&game.last_touch, &strategy.last_touch) {
+        (None, _) => false,
+        // NOTE not comparing teams, since the strategy may be kept from the other team's
+        // perspective, see Mirror
+        (Some(touch), Some(last_touch))
+            if touch.player_index == last_touch.player_index
+                && (touch.seconds_elapsed - last_touch.seconds_elapsed).abs() < SAME_TOUCH_SECONDS =>
+        {
+            false
+        }
+        (Some(touch), _) => touch.player_index != game.player_index,
+    }
+}
+
+/// the index into the ball trajectory where the ball goes into our own goal, if it does. expects
+/// a team-normalized trajectory, which play works out once per planning cycle, up to SAVE_HORIZON
+pub(crate) fn own_goal_entry_index(ball_trajectory: &[BallState], arena: &Arena) -> Option<usize> {
+    let goal = arena.goal(Team::Blue)?;
+    ball_trajectory.iter().position(|ball| goal.contains(&ball.position))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_strategy() -> Strategy {
+        Strategy {
+            action: Action::Shadow,
+            entry_score: 0.7,
+            started_frame: 100,
+            last_touch: Some(BallTouch {
+                player_index: 0,
+                team: Team::Blue,
+                seconds_elapsed: 10.0,
+            }),
+            failed_plans: 0,
+        }
+    }
+
+    fn test_game(frame: u32) -> GameState {
+        GameState {
+            last_touch: test_strategy().last_touch,
+            frame,
+            ..GameState::default()
+        }
+    }
+
+    #[test]
+    fn keeps_action_during_commitment_window() {
+        let strategy = test_strategy();
+        assert!(should_keep(&strategy, &test_game(110), 0.5, 1.0));
+
+        let after_window = 100 + (COMMITMENT_SECONDS / TICK) as u32 + 1;
+        assert!(!should_keep(&strategy, &test_game(after_window), 0.5, 1.0));
+        // not enough better to be worth switching
+        assert!(should_keep(&strategy, &test_game(after_window), 0.5, 0.55));
+    }
+
+    #[test]
+    fn expires_when_someone_else_touches_the_ball() {
+        let strategy = test_strategy();
+
+        let mut game = test_game(110);
+        // the same touch we started with, give or take float error
+        game.last_touch = Some(BallTouch {
+            player_index: 1,
+            team: Team::Orange,
+            seconds_elapsed: 10.0 + 1e-5,
+        });
+        let mut other_started = strategy.clone();
+        other_started.last_touch = Some(BallTouch {
+            player_index: 1,
+            team: Team::Orange,
+            seconds_elapsed: 10.0,
+        });
+        assert!(!expired(&other_started, &game, 0.5));
+
+        game.last_touch = Some(BallTouch {
+            player_index: 1,
+            team: Team::Orange,
+            seconds_elapsed: 10.5,
+        });
+        assert!(expired(&strategy, &game, 0.5));
+
+        // our own touches don't count
+        game.last_touch = Some(BallTouch {
+            player_index: 0,
+            team: Team::Blue,
+            seconds_elapsed: 10.5,
+        });
+        assert!(!expired(&strategy, &game, 0.5));
+    }
+
+    #[test]
+    fn expires_when_no_longer_applicable_or_planning_keeps_failing() {
+        let mut strategy = test_strategy();
+        assert!(expired(&strategy, &test_game(110), 0.0));
+
+        strategy.failed_plans = MAX_FAILED_PLANS;
+        assert!(expired(&strategy, &test_game(110), 0.5));
+    }
+
+    #[test]
+    fn expires_when_the_score_drops_well_below_the_entry_score() {
+        let strategy = test_strategy();
+        assert!(!expired(
+            &strategy,
+            &test_game(110),
+            strategy.entry_score - MAX_SCORE_DROP / 2.0
+        ));
+        assert!(expired(
+            &strategy,
+            &test_game(110),
+            strategy.entry_score - 2.0 * MAX_SCORE_DROP
+        ));
+    }
+}
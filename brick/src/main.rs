@@ -2,19 +2,23 @@ const USAGE: &str = "
 Brick
 
 Usage:
-  brick --bot
-  brick --bot-test
-  brick --simulate
+  brick --bot [--models=<dir>] [--hoops-mesh=<file>] [--dropshot-mesh=<file>]
+  brick --bot-test [--models=<dir>] [--hoops-mesh=<file>] [--dropshot-mesh=<file>]
+  brick --simulate [--kickoff=<spawn>] [--models=<dir>]
 
 Options:
-  -h --help     Show this screen.
-  --version     Show version.
-  --bot         Run regular bot in a match.
-  --bot-test    Run test bot during dev in an empty match.
-  --simulate    Run bot in a simulation of RL with visualization.
+  -h --help          Show this screen.
+  --version          Show version.
+  --bot              Run regular bot in a match.
+  --bot-test         Run test bot during dev in an empty match.
+  --simulate         Run bot in a simulation of RL with visualization.
+  --kickoff=<spawn>  Simulate a kickoff from a standard spawn: diagonal-left, diagonal-right,
+                     off-centre-left, off-centre-right or back-centre.
+  --models=<dir>     Directory of driving models [default: ./models/flat_ground].
+  --hoops-mesh=<file>     Hoops arena mesh, if not at ./assets/hoops.obj.
+  --dropshot-mesh=<file>  Dropshot arena mesh, if not at ./assets/dropshot.obj.
 ";
 
-extern crate bincode;
 extern crate brain;
 extern crate csv;
 extern crate docopt;
@@ -31,7 +35,7 @@ extern crate lazy_static;
 
 use docopt::Docopt;
 use std::cell::RefCell;
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 use std::error::Error;
 use std::f32;
 use std::f32::consts::PI;
@@ -53,7 +57,7 @@ use brain::predict;
 use kiss3d::light::Light;
 use kiss3d::resource::MeshManager;
 use kiss3d::window::Window;
-use na::{Point3, Quaternion, Rotation3, Translation3, UnitQuaternion, Vector3};
+use na::{Point3, Rotation3, Translation3, UnitQuaternion, Vector3};
 use spin_sleep::LoopHelper;
 
 type IoManipulator = fn(
@@ -73,12 +77,78 @@ struct BotIoConfig<'a> {
     render_debug_info: bool,
     save_debug_info: bool,
     record_history: bool,
+    print_search_stats: bool,
     start_match: bool,
     match_settings: Option<rlbot::MatchSettings<'a>>,
 }
 
 type Line = (Point3<f32>, Point3<f32>, Point3<f32>);
 
+/// how often we print search stats, in frames
+const SEARCH_STATS_INTERVAL: u32 = 10 * FPS as u32;
+
+/// search stats summed up over many plans, so we can tune the search config with data
+#[derive(Default)]
+struct SearchStatsSummary {
+    plans: u32,
+    partial_plans: u32,
+    /// plans that reached the goal, for averaging plan cost
+    complete_plans: u32,
+    plan_cost: f32,
+    totals: SearchStats,
+    terminations: HashMap<SearchTermination, u32>,
+}
+
+impl SearchStatsSummary {
+    fn add(&mut self, plan_result: &PlanResult) {
+        let stats = &plan_result.search_stats;
+        self.plans += 1;
+        if plan_result.partial {
+            self.partial_plans += 1;
+        }
+        self.totals.expansions += stats.expansions;
+        self.totals.pushes += stats.pushes;
+        self.totals.duplicates += stats.duplicates;
+        self.totals.pruned += stats.pruned;
+        self.totals.prediction_failures += stats.prediction_failures;
+        self.totals.shot_checks += stats.shot_checks;
+        self.totals.same_cell_expansions += stats.same_cell_expansions;
+        self.totals.max_open_set_size = self.totals.max_open_set_size.max(stats.max_open_set_size);
+        self.totals.heuristic_calls += stats.heuristic_calls;
+        self.totals.cells += stats.cells;
+        if let Some(cost) = stats.plan_cost {
+            self.complete_plans += 1;
+            self.plan_cost += cost;
+        }
+        self.totals.duration += stats.duration;
+        *self.terminations.entry(stats.termination).or_insert(0) += 1;
+    }
+
+    fn report(&self) -> String {
+        let plans = self.plans.max(1) as f32;
+        format!(
+            "search stats over {} plans ({} partial), per plan: {:.1} expansions, {:.1} pushes, {:.1} duplicates, \
+             {:.1} pruned, {:.1} prediction failures, {:.1} shot checks, {:.1} same cell expansions, {:.1} heuristic calls, {:.1} cells, {:.2}ms. \
+             max open set: {}. average cost of complete plans: {:.3}s. terminations: {:?}",
+            self.plans,
+            self.partial_plans,
+            self.totals.expansions as f32 / plans,
+            self.totals.pushes as f32 / plans,
+            self.totals.duplicates as f32 / plans,
+            self.totals.pruned as f32 / plans,
+            self.totals.prediction_failures as f32 / plans,
+            self.totals.shot_checks as f32 / plans,
+            self.totals.same_cell_expansions as f32 / plans,
+            self.totals.heuristic_calls as f32 / plans,
+            self.totals.cells as f32 / plans,
+            self.totals.duration.as_secs_f32() * 1000.0 / plans,
+            self.totals.max_open_set_size,
+            self.plan_cost / self.complete_plans.max(1) as f32,
+            self.terminations,
+        )
+    }
+}
+
 lazy_static! {
     static ref GAME_STATE: RwLock<GameState> = RwLock::new(GameState::default());
     static ref LINES: RwLock<Vec<Line>> = RwLock::new(vec![]);
@@ -90,11 +160,8 @@ fn run_visualization() {
 
     // we're dividing everything by 1000 until we can set the camera up to be more zoomed out
     let mut sphere = window.add_sphere(BALL_COLLISION_RADIUS / 1000.0);
-    let mut car = window.add_cube(
-        CAR_DIMENSIONS.x / 1000.0,
-        CAR_DIMENSIONS.y / 1000.0,
-        CAR_DIMENSIONS.z / 1000.0,
-    );
+    // scaled to the player's hitbox dimensions below
+    let mut car = window.add_cube(1.0, 1.0, 1.0);
 
     let arena_mesh = MeshManager::load_obj(Path::new("./assets/arena.obj"), Path::new("./assets/"), "arena")
         .expect("Can't load arena obj file")
@@ -127,6 +194,8 @@ fn run_visualization() {
         // we're dividing position by 1000 until we can set the camera up to be more zoomed out
         let hitbox_position = game_state.player.hitbox_center().map(|c| c / 1000.0);
         car.set_local_translation(Translation3::from(hitbox_position));
+        let hitbox_dimensions = game_state.player.hitbox.dimensions().map(|c| c / 1000.0);
+        car.set_local_scale(hitbox_dimensions.x, hitbox_dimensions.y, hitbox_dimensions.z);
         car.set_local_rotation(game_state.player.rotation);
 
         // grid for debugging
@@ -209,6 +278,7 @@ fn run_bot_test() {
         let bot_io_config = BotIoConfig {
             manipulator: Some(bot_test_manipulator),
             record_history: true,
+            print_search_stats: true,
             print_turn_errors: true,
             render_debug_info: true,
             save_debug_info: true,
@@ -221,51 +291,6 @@ fn run_bot_test() {
     bot_logic_loop_test(plan_sender, state_receiver);
 }
 
-#[allow(dead_code)]
-fn get_desired_car_state(player: &PlayerState) -> rlbot::DesiredCarState {
-    let pos = player.position;
-    let vel = player.velocity;
-    let avel = player.angular_velocity;
-
-    let uq = player.rotation;
-    let q = uq.quaternion();
-    // converting from left handed to left right coordinate system (goes with the x axis flip)
-    // see state::update_game_state
-    // https://stackoverflow.com/a/34366144/127219
-    let (roll, pitch, yaw) =
-        UnitQuaternion::from_quaternion(Quaternion::new(q.scalar(), -q.vector()[0], q.vector()[1], -q.vector()[2]))
-            .euler_angles();
-
-    let position = rlbot::Vector3Partial::new().x(-pos.x).y(pos.y).z(pos.z);
-    let velocity = rlbot::Vector3Partial::new().x(-vel.x).y(vel.y).z(vel.z);
-    let angular_velocity = rlbot::Vector3Partial::new().x(-avel.x).y(avel.y).z(avel.z);
-    let rotation = rlbot::RotatorPartial::new().pitch(pitch).yaw(yaw).roll(roll);
-    let physics = rlbot::DesiredPhysics::new()
-        .location(position)
-        .rotation(rotation)
-        .velocity(velocity)
-        .angular_velocity(angular_velocity);
-
-    rlbot::DesiredCarState::new().physics(physics)
-}
-
-#[allow(dead_code)]
-fn get_desired_ball_state(ball: &BallState) -> rlbot::DesiredBallState {
-    let pos = ball.position;
-    let vel = ball.velocity;
-    let avel = ball.angular_velocity;
-
-    let position = rlbot::Vector3Partial::new().x(-pos.x).y(pos.y).z(pos.z);
-    let velocity = rlbot::Vector3Partial::new().x(-vel.x).y(vel.y).z(vel.z);
-    let angular_velocity = rlbot::Vector3Partial::new().x(-avel.x).y(avel.y).z(avel.z);
-    let physics = rlbot::DesiredPhysics::new()
-        .location(position)
-        .velocity(velocity)
-        .angular_velocity(angular_velocity);
-
-    rlbot::DesiredBallState::new().physics(physics)
-}
-
 thread_local! {
     pub static SNAPSHOT_NUMBER: RefCell<u32> = RefCell::new(1);
 }
@@ -288,16 +313,31 @@ fn record_snapshot(game: &GameState, bot: &BotState) -> Result<(), Box<dyn Error
     });
     let f = BufWriter::new(File::create(file_path)?);
     let mut e = GzEncoder::new(f, Compression::default());
-    Ok(bincode::serialize_into(&mut e, &(game, bot))?)
+    Snapshot::new(game, bot).write(&mut e)
 }
 
-#[allow(dead_code)]
-fn restore_snapshot(rlbot: &rlbot::RLBot, bot: &mut BotState, frame: &mut u32, name: &str) -> Result<(), Box<dyn Error>> {
+fn load_snapshot(name: &str) -> Result<Snapshot, Box<dyn Error>> {
     let dir = "data/snapshots";
     let path = Path::new(dir).join(name.to_owned() + ".bincode.gz");
     let f = BufReader::new(File::open(path)?);
     let mut decoder = GzDecoder::new(f);
-    let (historical_game, historical_bot): (GameState, BotState) = bincode::deserialize_from(&mut decoder)?;
+    Snapshot::read(&mut decoder)
+}
+
+/// writes a json copy of the snapshot next to it, for looking into it by eye
+#[allow(dead_code)]
+fn export_snapshot_json(name: &str) -> Result<(), Box<dyn Error>> {
+    let json = load_snapshot(name)?.to_json()?;
+    let path = Path::new("data/snapshots").join(name.to_owned() + ".json");
+    Ok(std::fs::write(path, json)?)
+}
+
+#[allow(dead_code)]
+fn restore_snapshot(rlbot: &rlbot::RLBot, bot: &mut BotState, frame: &mut u32, name: &str) -> Result<(), Box<dyn Error>> {
+    let Snapshot {
+        game: historical_game,
+        bot: historical_bot,
+    } = load_snapshot(name)?;
 
     // replace our bot data with the historical bot
     *bot = historical_bot;
@@ -306,8 +346,8 @@ fn restore_snapshot(rlbot: &rlbot::RLBot, bot: &mut BotState, frame: &mut u32, n
     *frame = historical_game.frame;
     let player = historical_game.player;
     let ball = historical_game.ball;
-    let car_state = get_desired_car_state(&player);
-    let ball_state = get_desired_ball_state(&ball);
+    let car_state = rlbot::DesiredCarState::from(&player);
+    let ball_state = rlbot::DesiredBallState::from(&ball);
     let desired_game_state = rlbot::DesiredGameState::new().car_state(0, car_state).ball_state(ball_state);
     Ok(rlbot.set_game_state(&desired_game_state)?)
 }
@@ -376,8 +416,8 @@ fn bot_test_manipulator(
     //    ball.velocity.x = 600.0;
     //    player.velocity.y = 100.0;
 
-    //    let car_state = get_desired_car_state(&player);
-    //    let ball_state = get_desired_ball_state(&ball);
+    //    let car_state = rlbot::DesiredCarState::from(&player);
+    //    let ball_state = rlbot::DesiredBallState::from(&ball);
     //    let desired_game_state = rlbot::DesiredGameState::new().car_state(0, car_state).ball_state(ball_state);
     //    rlbot.set_game_state(&desired_game_state)?;
 
@@ -586,12 +626,22 @@ fn bot_io_loop(sender: Sender<(GameState, BotState)>, receiver: Receiver<PlanRes
     let mut frame = 0u32;
     let mut logic_lag = 0u32; // measured in frames
     let mut history = VecDeque::new();
+    let mut search_stats = SearchStatsSummary::default();
 
     let mut csv_writer = csv::Writer::from_path("debug.csv").expect("csv writer construction failed");
 
     let mut last_time = 0.0;
+    let mut has_field_info = false;
     loop {
         loop_helper.loop_start();
+
+        if !has_field_info {
+            if let Some(field_info) = rlbot.interface().update_field_info_flatbuffer() {
+                update_field_info(&mut GAME_STATE.write().unwrap(), &field_info);
+                has_field_info = true;
+            }
+        }
+
         if let Some(tick) = try_next_flat(&rlbot, last_time) {
             last_time = tick.game_info.seconds_elapsed;
             update_game_state(&mut GAME_STATE.write().unwrap(), &tick, player_index, frame);
@@ -621,6 +671,8 @@ fn bot_io_loop(sender: Sender<(GameState, BotState)>, receiver: Receiver<PlanRes
                 // accurate debug logging of the planned player values
                 stitch_with_current_plan(&GAME_STATE.read().unwrap(), &bot, &mut plan_result);
 
+                search_stats.add(&plan_result);
+
                 update_bot_state(&GAME_STATE.read().unwrap(), &mut bot, &plan_result);
                 match update_in_game_visualization(&rlbot, &bot, &plan_result) {
                     Ok(_) => {}
@@ -628,6 +680,11 @@ fn bot_io_loop(sender: Sender<(GameState, BotState)>, receiver: Receiver<PlanRes
                 };
             }
 
+            if bot_io_config.print_search_stats && frame % SEARCH_STATS_INTERVAL == 0 && search_stats.plans > 0 {
+                println!("{}", search_stats.report());
+                search_stats = SearchStatsSummary::default();
+            }
+
             // // remove part of plan that is no longer relevant since we've already passed it
             // if let Some(ref mut plan) = bot.plan {
             //     let closest_index = brain::play::closest_plan_index(&GAME_STATE.read().unwrap().player, &plan);
@@ -841,7 +898,12 @@ fn stitch_with_current_plan(game: &GameState, bot: &BotState, plan_result: &mut
 fn plan_is_valid(game: &GameState, plan: &[PlanStep]) -> bool {
     let closest_index = brain::play::closest_plan_index(&game.player, &plan);
     if let Some((player, _, _)) = plan.get(closest_index) {
-        let ball_trajectory = predict::ball::ball_trajectory(&game.ball, (plan.len() - 1 - closest_index) as f32 * TICK);
+        let duration = (plan.len() - 1 - closest_index) as f32 * TICK;
+        let arena = match predict::arena::arena(game.arena) {
+            Ok(arena) => arena,
+            Err(_) => return false,
+        };
+        let ball_trajectory = predict::ball::ball_trajectory(&game.ball, duration, &arena);
         let is_player_accurate = (player.position - game.player.position).norm() < 30.0
             && (player.velocity - game.player.velocity).norm() < 200.0;
 
@@ -857,6 +919,16 @@ fn plan_is_valid(game: &GameState, plan: &[PlanStep]) -> bool {
 }
 
 fn update_bot_state(game: &GameState, bot: &mut BotState, plan_result: &PlanResult) {
+    // a plan for a different action isn't comparable with the existing one, so always take it
+    let switched_action = match (&bot.strategy, &plan_result.strategy) {
+        (Some(existing), Some(new)) => existing.action != new.action,
+        (None, Some(_)) => true,
+        _ => false,
+    };
+    if plan_result.strategy.is_some() {
+        bot.strategy = plan_result.strategy.clone();
+    }
+
     if let Some(ref new_plan) = plan_result.plan {
         if let Some(ref existing_plan) = bot.plan {
             let new_plan_cost = new_plan.iter().map(|(_, _, cost)| cost).sum::<f32>();
@@ -869,8 +941,10 @@ fn update_bot_state(game: &GameState, bot: &mut BotState, plan_result: &PlanResu
                 .map(|(_index, (_, _, cost))| cost)
                 .sum::<f32>();
 
-            // bail, we got a worse plan!
-            if new_plan_cost >= existing_plan_cost && plan_is_valid(&game, &existing_plan) {
+            // bail, we got a worse plan! a partial plan doesn't get us all the way there, so it's
+            // only better than having no plan
+            let new_plan_is_worse = plan_result.partial || new_plan_cost >= existing_plan_cost;
+            if !switched_action && new_plan_is_worse && plan_is_valid(&game, &existing_plan) {
                 //println!("bailing longer plan! existing_plan_cost: {}, new_plan_cost: {}", existing_plan_cost, new_plan_cost);
                 return;
             }
@@ -999,15 +1073,16 @@ fn icosahedron_lines(ball: &BallState) -> Vec<Line> {
 }
 
 fn hitbox_lines(player: &PlayerState) -> Vec<Line> {
+    let d = player.hitbox.dimensions();
     let mut vertices = [
-        Vector3::new(CAR_DIMENSIONS.x, CAR_DIMENSIONS.y, CAR_DIMENSIONS.z),
-        Vector3::new(-CAR_DIMENSIONS.x, CAR_DIMENSIONS.y, CAR_DIMENSIONS.z),
-        Vector3::new(CAR_DIMENSIONS.x, -CAR_DIMENSIONS.y, CAR_DIMENSIONS.z),
-        Vector3::new(-CAR_DIMENSIONS.x, -CAR_DIMENSIONS.y, CAR_DIMENSIONS.z),
-        Vector3::new(CAR_DIMENSIONS.x, CAR_DIMENSIONS.y, -CAR_DIMENSIONS.z),
-        Vector3::new(-CAR_DIMENSIONS.x, CAR_DIMENSIONS.y, -CAR_DIMENSIONS.z),
-        Vector3::new(CAR_DIMENSIONS.x, -CAR_DIMENSIONS.y, -CAR_DIMENSIONS.z),
-        Vector3::new(-CAR_DIMENSIONS.x, -CAR_DIMENSIONS.y, -CAR_DIMENSIONS.z),
+        Vector3::new(d.x, d.y, d.z),
+        Vector3::new(-d.x, d.y, d.z),
+        Vector3::new(d.x, -d.y, d.z),
+        Vector3::new(-d.x, -d.y, d.z),
+        Vector3::new(d.x, d.y, -d.z),
+        Vector3::new(-d.x, d.y, -d.z),
+        Vector3::new(d.x, -d.y, -d.z),
+        Vector3::new(-d.x, -d.y, -d.z),
     ]
     .iter()
     .map(|v| player.hitbox_center() + player.rotation.to_rotation_matrix() * (0.5 * v))
@@ -1122,9 +1197,11 @@ fn send_to_bot_logic(sender: &Sender<(GameState, BotState)>, bot: &BotState, log
         if let Some((player, _, _)) = plan.get(index) {
             game.player = player.clone();
         }
-        game.ball = predict::ball::ball_trajectory(&game.ball, logic_lag as f32 * TICK)
-            .pop()
-            .expect("Missing ball trajectory");
+        if let Ok(arena) = predict::arena::arena(game.arena) {
+            game.ball = predict::ball::ball_trajectory(&game.ball, logic_lag as f32 * TICK, &arena)
+                .pop()
+                .expect("Missing ball trajectory");
+        }
     }
 
     sender.send((game, bot.clone())).expect("Sending to bot logic failed");
@@ -1336,7 +1413,38 @@ fn offset_forward_plan(current: &PlayerState) -> Result<Plan, Box<dyn Error>> {
     forward_plan(&offset_player, 4000.0)
 }
 
-fn simulate_over_time() {
+/// blue at the start of a kickoff from the given standard spawn, eg diagonal-left
+fn kickoff_game_state(spawn_name: &str) -> Result<GameState, String> {
+    use brain::play::KickoffSpawn;
+
+    let (spawn, side) = match spawn_name {
+        "diagonal-left" => (KickoffSpawn::Diagonal, -1.0),
+        "diagonal-right" => (KickoffSpawn::Diagonal, 1.0),
+        "off-centre-left" => (KickoffSpawn::OffCentre, -1.0),
+        "off-centre-right" => (KickoffSpawn::OffCentre, 1.0),
+        "back-centre" => (KickoffSpawn::BackCentre, 1.0),
+        _ => return Err(format!("Unknown kickoff spawn: {}", spawn_name)),
+    };
+
+    let mut game_state = GameState::default();
+    game_state.match_info.is_kickoff_pause = true;
+    game_state.ball.position = Vector3::new(0.0, 0.0, BALL_COLLISION_RADIUS);
+    game_state.player.position = spawn.position();
+    game_state.player.position.x *= side;
+    game_state.player.boost = 33.0;
+
+    // diagonal spawns face the ball, the rest face straight upfield
+    let heading = match spawn {
+        KickoffSpawn::Diagonal => -game_state.player.position,
+        _ => Vector3::new(0.0, 1.0, 0.0),
+    };
+    // the car with no rotation points towards negative x
+    game_state.player.rotation = UnitQuaternion::from_euler_angles(0.0, 0.0, heading.y.atan2(heading.x) - PI);
+
+    Ok(game_state)
+}
+
+fn simulate_over_time(kickoff: Option<GameState>) {
     thread::sleep(Duration::from_millis(5000));
     let initial_game_state: GameState;
     let mut bot = BotState::default();
@@ -1344,7 +1452,10 @@ fn simulate_over_time() {
 
     let mut loop_helper = LoopHelper::builder().build_with_target_rate(120.0); // simulation limited to 120 FPS
 
-    {
+    if let Some(kickoff) = kickoff {
+        *GAME_STATE.write().unwrap() = kickoff.clone();
+        initial_game_state = kickoff;
+    } else {
         let mut game_state = GAME_STATE.write().unwrap();
         game_state.ball.position = Vector3::new(0.0, 0.0, BALL_COLLISION_RADIUS);
         game_state.ball.velocity = Vector3::new(400.0, 400.0, 0.0);
@@ -1379,7 +1490,8 @@ fn simulate_over_time() {
 
         if let Some(plan) = bot.plan.clone() {
             let mut game_state = GAME_STATE.write().unwrap();
-            game_state.ball = predict::ball::next_ball_state(&game_state.ball, TICK);
+            // the simulation is always soccar
+            game_state.ball = predict::ball::next_ball_state(&game_state.ball, TICK, &predict::arena::SOCCAR_ARENA);
             let i = brain::play::closest_plan_index(&game_state.player, &plan);
             if plan.len() >= i + 2 {
                 game_state.player = plan[i + 1].0.clone();
@@ -1401,6 +1513,18 @@ fn simulate_over_time() {
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Docopt::new(USAGE).and_then(|dopt| dopt.parse()).unwrap_or_else(|e| e.exit());
 
+    // load up front, so that missing models are reported before we start playing
+    predict::driving_model::set_model_dir(args.get_str("--models"));
+    predict::driving_model::models_for(Hitbox::Octane)?;
+
+    // only loaded once we're in a match on that arena, we don't plan at all if that fails
+    if !args.get_str("--hoops-mesh").is_empty() {
+        predict::arena::set_arena_mesh(ArenaKind::Hoops, args.get_str("--hoops-mesh"));
+    }
+    if !args.get_str("--dropshot-mesh").is_empty() {
+        predict::arena::set_arena_mesh(ArenaKind::Dropshot, args.get_str("--dropshot-mesh"));
+    }
+
     let test_bot = args.get_bool("--bot-test");
     if args.get_bool("--bot") || test_bot {
         thread::spawn(move || loop {
@@ -1422,7 +1546,11 @@ fn main() -> Result<(), Box<dyn Error>> {
             thread::sleep(Duration::from_millis(1000));
         });
     } else if args.get_bool("--simulate") {
-        thread::spawn(simulate_over_time);
+        let kickoff = match args.get_str("--kickoff") {
+            "" => None,
+            spawn_name => Some(kickoff_game_state(spawn_name)?),
+        };
+        thread::spawn(move || simulate_over_time(kickoff));
     } else {
         panic!("Must provide --bot, --bot-test or --simulate");
     }
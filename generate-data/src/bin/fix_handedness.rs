@@ -22,6 +22,9 @@ fn main() -> Result<(), Box<dyn Error>> {
             let pos = player.position;
             let vel = player.velocity;
             let avel = player.angular_velocity;
+            // NOTE this is a one-off migration of old sample files, which stored rotations with a
+            // different quaternion handedness than rlbot or us. it's deliberately not using the
+            // state rlbot conversion functions, since the old files aren't in rlbot's coordinates
             let rlq = player.rotation.quaternion().coords;
             let translated = UnitQuaternion::from_quaternion(Quaternion::new(rlq[3], rlq[0], -rlq[1], -rlq[2]));
             let (roll, pitch, yaw) = translated.euler_angles();
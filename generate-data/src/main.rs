@@ -44,11 +44,16 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     let config = SearchConfig {
         step_duration: 16.0 * TICK,
+        step_schedule: vec![],
         slop: 40.0,
         max_cost: 10.0,
         max_iterations: 10_000_000, // allow more iterations before giving up
         scale_heuristic: 1.0,
         custom_filter: Some(|_| true), // ignore bounds
+        analog_branches: false,
+        boost_cost: 0.0,
+        deadline: None,
+        cell: CellConfig::default(),
     };
 
     let max_speed_r = (MAX_BOOST_SPEED / SPEED_FACTOR).round() as i32;
@@ -88,8 +93,10 @@ fn main() -> Result<(), Box<dyn Error>> {
                                 &mut model,
                                 &player,
                                 &[ball.clone()],
+                                &brain::predict::arena::SOCCAR_ARENA,
                                 0,
                                 &desired_contact,
+                                &plan::HitIntoGoal,
                                 0.0, // doesn't matter, used just to keep track of when we think we should hit it
                                 &config,
                             )
@@ -89,13 +89,17 @@ impl HeuristicModel for BasicHeuristic {
 
     fn configure(&mut self, desired: &DesiredContact, scale: f32) {
         self.desired_heading = Unit::new_normalize(desired.heading).into_inner();
-        self.goal_center = desired.position - (CAR_DIMENSIONS.x / 2.0) * self.desired_heading;
+        // NOTE the heuristic isn't given the player, so this assumes the default hitbox. it only
+        // needs to be roughly right
+        let hitbox = Hitbox::default();
+        self.goal_center = desired.position - (hitbox.dimensions().x / 2.0) * self.desired_heading;
         self.scale = scale;
     }
 
     fn ball_configure(&mut self, ball: &BallState, ball_goal: &Vector3<f32>) {
         self.desired_heading = Unit::new_normalize(ball_goal - ball.position).into_inner();
-        self.goal_center =
-            ball.position - (BALL_COLLISION_RADIUS + (CAR_DIMENSIONS.x / 2.0) + CAR_OFFSET.x.abs()) * self.desired_heading;
+        let hitbox = Hitbox::default();
+        self.goal_center = ball.position
+            - (BALL_COLLISION_RADIUS + (hitbox.dimensions().x / 2.0) + hitbox.offset().x.abs()) * self.desired_heading;
     }
 }
@@ -1,27 +1,254 @@
-use na::Point3;
+use error::PredictError;
+use na::{Point3, Vector3};
 use ncollide::shape::TriMesh;
 use obj::*;
+use state::*;
+use std::collections::HashMap;
+use std::error::Error;
 use std::fs::File;
 use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
-lazy_static! {
-    pub static ref ARENA: TriMesh<f32> = {
-        let file = File::open("./assets/arena.obj").expect("Couldn't open arena.obj file");
-        let input = BufReader::new(file);
-        let arena: Obj = load_obj(input).expect("failed to parse arena.obj file in predict");
+/// axis aligned box that the ball's center must be in for a goal to be scored
+#[derive(Debug, Clone)]
+pub struct GoalVolume {
+    /// the team defending this goal
+    pub team: Team,
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl GoalVolume {
+    pub fn contains(&self, position: &Vector3<f32>) -> bool {
+        position.x >= self.min.x
+            && position.x <= self.max.x
+            && position.y >= self.min.y
+            && position.y <= self.max.y
+            && position.z >= self.min.z
+            && position.z <= self.max.z
+    }
+
+    /// a goal only counts once the whole ball is over the goal line, not just its center
+    pub fn is_scored(&self, position: &Vector3<f32>, ball_radius: f32) -> bool {
+        let past_goal_line = match self.team {
+            Team::Blue => self.max.y - position.y,
+            Team::Orange => position.y - self.min.y,
+        };
+        self.contains(position) && past_goal_line >= ball_radius
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) / 2.0
+    }
+}
+
+/// everything about the arena that prediction and planning depend on
+pub struct Arena {
+    pub kind: ArenaKind,
+    pub mesh: TriMesh<f32>,
+    pub goals: Vec<GoalVolume>,
+    pub side_wall_distance: f32,
+    pub side_curve_distance: f32,
+    pub back_wall_distance: f32,
+    pub back_curve_distance: f32,
+    pub ceiling_distance: f32,
+    pub ball_collision_radius: f32,
+    pub ball_inertial_radius: f32,
+}
+
+// soccar goals are this deep, measured from BACK_WALL_DISTANCE
+const SOCCAR_GOAL_DEPTH: f32 = 880.0;
 
-        let vertices: Vec<Point3<f32>> = arena.vertices.iter().map(|vert|
-            Point3::new(vert.position[0], vert.position[1], vert.position[2])
-        ).collect();
+// NOTE the walls curve into the floor over roughly this distance. we only know it for soccar, so
+// use it as an approximation for the other arenas too
+const CURVE_DISTANCE: f32 = SIDE_WALL_DISTANCE - SIDE_CURVE_DISTANCE;
 
-        let indices: Vec<Point3<usize>> = arena.indices.chunks(3).map(|indices|
-            Point3::new(indices[0] as usize, indices[1] as usize, indices[2] as usize)
-        ).collect();
+impl Arena {
+    /// loads the given mesh, which can be a custom one. the dimensions are known for soccar, but
+    /// for the other arenas we take them from the mesh bounds
+    pub fn load(kind: ArenaKind, mesh_path: &str) -> Result<Arena, Box<dyn Error>> {
+        let input = BufReader::new(File::open(mesh_path)?);
+        let obj: Obj = load_obj(input)?;
 
-        TriMesh::new(
+        let vertices: Vec<Point3<f32>> = obj
+            .vertices
+            .iter()
+            .map(|vert| Point3::new(vert.position[0], vert.position[1], vert.position[2]))
+            .collect();
+
+        let indices: Vec<Point3<usize>> = obj
+            .indices
+            .chunks(3)
+            .map(|indices| Point3::new(indices[0] as usize, indices[1] as usize, indices[2] as usize))
+            .collect();
+
+        let max_x = vertices.iter().map(|v| v.x.abs()).fold(0.0, f32::max);
+        let max_y = vertices.iter().map(|v| v.y.abs()).fold(0.0, f32::max);
+        let max_z = vertices.iter().map(|v| v.z).fold(0.0, f32::max);
+
+        let mesh = TriMesh::new(
             vertices, // Vec<P>,
-            indices, // Vec<Point<usize, U3>>,
-            None, // uvs: Option<Vec<Point2<N>>>,
-        )
+            indices,  // Vec<Point<usize, U3>>,
+            None,     // uvs: Option<Vec<Point2<N>>>,
+        );
+
+        let arena = match kind {
+            ArenaKind::Soccar => Arena {
+                kind,
+                mesh,
+                goals: vec![
+                    GoalVolume {
+                        team: Team::Blue,
+                        min: Vector3::new(-GOAL_X, -BACK_WALL_DISTANCE - SOCCAR_GOAL_DEPTH, 0.0),
+                        max: Vector3::new(GOAL_X, -BACK_WALL_DISTANCE, GOAL_Z),
+                    },
+                    GoalVolume {
+                        team: Team::Orange,
+                        min: Vector3::new(-GOAL_X, BACK_WALL_DISTANCE, 0.0),
+                        max: Vector3::new(GOAL_X, BACK_WALL_DISTANCE + SOCCAR_GOAL_DEPTH, GOAL_Z),
+                    },
+                ],
+                side_wall_distance: SIDE_WALL_DISTANCE,
+                side_curve_distance: SIDE_CURVE_DISTANCE,
+                back_wall_distance: BACK_WALL_DISTANCE,
+                back_curve_distance: BACK_CURVE_DISTANCE,
+                ceiling_distance: CEILING_DISTANCE,
+                ball_collision_radius: BALL_COLLISION_RADIUS,
+                ball_inertial_radius: BALL_INERTIAL_RADIUS,
+            },
+            ArenaKind::Hoops => Arena {
+                kind,
+                mesh,
+                goals: vec![], // TODO model the hoop rims
+                side_wall_distance: max_x,
+                side_curve_distance: max_x - CURVE_DISTANCE,
+                back_wall_distance: max_y,
+                back_curve_distance: max_y - CURVE_DISTANCE,
+                ceiling_distance: max_z,
+                // source: https://github.com/samuelpmish/RLUtilities/blob/master/src/simulation/ball.cc
+                ball_collision_radius: 93.15, // TODO double check
+                ball_inertial_radius: 91.25,  // TODO double check
+            },
+            ArenaKind::Dropshot => Arena {
+                kind,
+                mesh,
+                goals: vec![], // scoring is by breaking floor tiles, there are no goals
+                side_wall_distance: max_x,
+                side_curve_distance: max_x - CURVE_DISTANCE,
+                back_wall_distance: max_y,
+                back_curve_distance: max_y - CURVE_DISTANCE,
+                ceiling_distance: max_z,
+                // source: https://github.com/samuelpmish/RLUtilities/blob/master/src/simulation/ball.cc
+                ball_collision_radius: 103.6, // TODO double check
+                ball_inertial_radius: 100.45, // TODO double check
+            },
+        };
+
+        Ok(arena)
+    }
+
+    /// the goal defended by the given team
+    pub fn goal(&self, team: Team) -> Option<&GoalVolume> {
+        self.goals.iter().find(|goal| goal.team == team)
+    }
+
+    /// whether the position is past the curves at the bottom of the walls, ie somewhere we can't
+    /// predict driving yet
+    pub fn beyond_curves(&self, position: &Vector3<f32>, margin: f32) -> bool {
+        position.x.abs() > self.side_curve_distance + margin || position.y.abs() > self.back_curve_distance + margin
+    }
+
+    pub fn ball_inertia(&self, ball_mass: f32) -> f32 {
+        0.4 * ball_mass * self.ball_inertial_radius * self.ball_inertial_radius
+    }
+}
+
+lazy_static! {
+    /// the checked-in soccar mesh, regardless of set_arena_mesh
+    pub static ref SOCCAR_ARENA: Arena =
+        Arena::load(ArenaKind::Soccar, default_mesh_path(ArenaKind::Soccar)).expect("Couldn't load soccar arena");
+    /// meshes to use instead of the default ones
+    static ref ARENA_MESHES: RwLock<HashMap<ArenaKind, PathBuf>> = RwLock::new(HashMap::new());
+    /// failures are kept so that we don't retry loading every frame
+    static ref ARENAS: RwLock<HashMap<ArenaKind, Result<Arc<Arena>, PredictError>>> = RwLock::new(HashMap::new());
+}
+
+/// NOTE only the soccar mesh is checked in. the others need to be exported to these paths, or
+/// given with set_arena_mesh, before playing those modes
+pub fn default_mesh_path(kind: ArenaKind) -> &'static str {
+    match kind {
+        ArenaKind::Soccar => "./assets/arena.obj",
+        ArenaKind::Hoops => "./assets/hoops.obj",
+        ArenaKind::Dropshot => "./assets/dropshot.obj",
+    }
+}
+
+/// use a custom mesh for the given kind of arena
+pub fn set_arena_mesh<P: AsRef<Path>>(kind: ArenaKind, mesh_path: P) {
+    ARENA_MESHES
+        .write()
+        .expect("Arena meshes lock poisoned")
+        .insert(kind, mesh_path.as_ref().to_owned());
+    ARENAS.write().expect("Arenas lock poisoned").remove(&kind);
+}
+
+/// the arena for the given kind, loaded the first time it's needed
+pub fn arena(kind: ArenaKind) -> Result<Arc<Arena>, PredictError> {
+    if let Some(arena) = ARENAS.read().expect("Arenas lock poisoned").get(&kind) {
+        return arena.clone();
+    }
+
+    let mesh_path = match ARENA_MESHES.read().expect("Arena meshes lock poisoned").get(&kind) {
+        Some(mesh_path) => mesh_path.clone(),
+        None => PathBuf::from(default_mesh_path(kind)),
     };
+    let arena = Arena::load(kind, &mesh_path.to_string_lossy())
+        .map(Arc::new)
+        .map_err(|e| PredictError::ArenaLoad {
+            path: mesh_path.display().to_string(),
+            error: e.to_string(),
+        });
+    if let Err(ref e) = arena {
+        eprintln!("Error: {}", e);
+    }
+
+    ARENAS
+        .write()
+        .expect("Arenas lock poisoned")
+        .entry(kind)
+        .or_insert(arena)
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn goal(team: Team) -> GoalVolume {
+        let side = if team == Team::Blue { -1.0 } else { 1.0 };
+        let (near, far) = (side * BACK_WALL_DISTANCE, side * (BACK_WALL_DISTANCE + SOCCAR_GOAL_DEPTH));
+        GoalVolume {
+            team,
+            min: Vector3::new(-GOAL_X, near.min(far), 0.0),
+            max: Vector3::new(GOAL_X, near.max(far), GOAL_Z),
+        }
+    }
+
+    #[test]
+    fn scored_once_the_whole_ball_is_over_the_line() {
+        for &team in &[Team::Blue, Team::Orange] {
+            let goal = goal(team);
+            let side = if team == Team::Blue { -1.0 } else { 1.0 };
+            let at = |depth: f32| Vector3::new(0.0, side * (BACK_WALL_DISTANCE + depth), BALL_COLLISION_RADIUS);
+
+            assert!(!goal.is_scored(&at(-10.0), BALL_COLLISION_RADIUS));
+            assert!(!goal.is_scored(&at(BALL_COLLISION_RADIUS / 2.0), BALL_COLLISION_RADIUS));
+            assert!(goal.is_scored(&at(BALL_COLLISION_RADIUS + 1.0), BALL_COLLISION_RADIUS));
+
+            let mut wide = at(BALL_COLLISION_RADIUS + 1.0);
+            wide.x = GOAL_X + 1.0;
+            assert!(!goal.is_scored(&wide, BALL_COLLISION_RADIUS));
+        }
+    }
 }
@@ -1,10 +1,10 @@
-use std::error::Error;
 use std::f32;
 
 use na::{self, Isometry3, Unit, Vector3};
 use ncollide;
 
-use arena::ARENA;
+use arena::Arena;
+use error::PredictError;
 use state::*;
 
 const RESTITUTION: f32 = 0.6; // was: C_R = 0.6
@@ -22,20 +22,19 @@ const BALL_MAX_SPEED: f32 = 6000.0; // uu/s
 const BALL_MAX_ROTATION_SPEED: f32 = 6.0; // rad/s
 
 const BALL_MASS: f32 = 30.0;
-const BALL_INERTIA: f32 = 0.4 * BALL_MASS * BALL_INERTIAL_RADIUS * BALL_INERTIAL_RADIUS;
 
 enum PredictionCategory {
     Soaring,
     Rolling,
 }
 
-fn find_prediction_category(ball: &BallState) -> PredictionCategory {
+fn find_prediction_category(ball: &BallState, arena: &Arena) -> PredictionCategory {
     // NOTE using the "soaring" calculations when interacting with wall/curves, even when rolling,
     // since we at least have arena collision for that and will get a better prediction even if not
     // 100% accurate
-    let in_air = ball.position.z > BALL_COLLISION_RADIUS || ball.velocity.z.abs() < 1.0;
-    let on_side_curve = ball.position.x.abs() > SIDE_CURVE_DISTANCE;
-    let on_back_curve = ball.position.y.abs() > BACK_CURVE_DISTANCE;
+    let in_air = ball.position.z > arena.ball_collision_radius || ball.velocity.z.abs() < 1.0;
+    let on_side_curve = ball.position.x.abs() > arena.side_curve_distance;
+    let on_back_curve = ball.position.y.abs() > arena.back_curve_distance;
     if in_air || on_side_curve || on_back_curve {
         PredictionCategory::Soaring
     } else {
@@ -43,46 +42,63 @@ fn find_prediction_category(ball: &BallState) -> PredictionCategory {
     }
 }
 
-pub fn ball_trajectory(ball: &BallState, duration: f32) -> Vec<BallState> {
+pub fn ball_trajectory(ball: &BallState, duration: f32, arena: &Arena) -> Vec<BallState> {
     let mut t = 0.0;
     let mut trajectory = Vec::with_capacity((duration / TICK).ceil() as usize);
     let mut ball_now = ball.clone();
     trajectory.push(ball_now);
     while t < duration {
         t += TICK;
-        ball_now = next_ball_state(trajectory.last().unwrap(), TICK);
+        ball_now = next_ball_state(trajectory.last().unwrap(), TICK, arena);
         trajectory.push(ball_now);
     }
     trajectory
 }
 
-pub fn trajectory_enters_soccar_goal(ball: &BallState) -> bool {
-    // FIXME check full ball trajectory, with bouncing
-    //let goal = Vector3::new(0.0, BACK_WALL_DISTANCE, GOAL_Z / 2.0);
-    let goal = na::Vector2::new(0.0, BACK_WALL_DISTANCE);
-    let pos = na::Vector2::new(ball.position.x, ball.position.y);
-    let v = na::Vector2::new(ball.velocity.x, ball.velocity.y);
-
-    // check if 2d ball velocty is towards goal
-    // TODO use exact angle of left side and right side of goal from current ball position instead
-    // of the dot product approximate check
-    (goal - pos).normalize().dot(&v.normalize()) > 0.95
+/// rolls the ball forward for up to the given number of seconds and returns how long it takes to
+/// score in the goal at +y, if it does. it bounces off the arena mesh on the way, which includes
+/// the posts and crossbar, so bank shots count and shots off the post don't. checks against the
+/// goal at +y, so the ball should be team-normalized (see state::Mirror)
+pub fn trajectory_enters_goal(ball: &BallState, seconds: f32, arena: &Arena) -> Option<f32> {
+    let goal = arena.goal(Team::Orange)?;
+    let ticks = (seconds / TICK).ceil() as usize;
+    let mut ball = ball.clone();
+    for tick in 0..=ticks {
+        if goal.is_scored(&ball.position, arena.ball_collision_radius) {
+            return Some(tick as f32 * TICK);
+        }
+
+        // this gets called for every hit we find while planning, so stop as soon as it can't go
+        // in: it's somehow past the back wall without being in the goal, or it's heading away
+        // from the goal, eg off the post, with no time left to come back off our own back wall
+        let past_back_wall = ball.position.y > arena.back_wall_distance && !goal.contains(&ball.position);
+        let remaining_seconds = (ticks - tick) as f32 * TICK;
+        let to_own_back_wall = ball.position.y + arena.back_wall_distance - arena.ball_collision_radius;
+        let gone = ball.velocity.y <= 0.0 && -ball.velocity.y * remaining_seconds < to_own_back_wall;
+        if past_back_wall || gone {
+            return None;
+        }
+
+        ball = next_ball_state(&ball, TICK, arena);
+    }
+
+    None
 }
 
-pub fn next_ball_state(ball: &BallState, time_step: f32) -> BallState {
-    match find_prediction_category(&ball) {
-        PredictionCategory::Soaring => next_ball_state_soaring(&ball, time_step),
-        PredictionCategory::Rolling => next_ball_state_rolling(&ball, time_step),
+pub fn next_ball_state(ball: &BallState, time_step: f32, arena: &Arena) -> BallState {
+    match find_prediction_category(&ball, arena) {
+        PredictionCategory::Soaring => next_ball_state_soaring(&ball, time_step, arena),
+        PredictionCategory::Rolling => next_ball_state_rolling(&ball, time_step, arena),
     }
 }
 
-fn next_ball_state_soaring(ball: &BallState, time_step: f32) -> BallState {
+fn next_ball_state_soaring(ball: &BallState, time_step: f32, arena: &Arena) -> BallState {
     let mut next;
 
-    if let Some(normal) = arena_contact_normal(&ball) {
+    if let Some(normal) = arena_contact_normal(&ball, arena) {
         if na::Matrix::dot(&ball.velocity, &normal) < 0.0 {
             // we're going towards the arena contact, so let's bounce
-            next = calculate_bounce(&ball, &normal);
+            next = calculate_bounce(&ball, &normal, arena);
         } else {
             // already bounced
             next = ball.clone();
@@ -109,7 +125,7 @@ fn next_ball_state_soaring(ball: &BallState, time_step: f32) -> BallState {
 }
 
 // source: https://www.youtube.com/watch?v=9uh8-nBlufM
-fn next_ball_state_rolling(ball: &BallState, time_step: f32) -> BallState {
+fn next_ball_state_rolling(ball: &BallState, time_step: f32, arena: &Arena) -> BallState {
     let mut next = ball.clone();
 
     let acceleration;
@@ -125,31 +141,31 @@ fn next_ball_state_rolling(ball: &BallState, time_step: f32) -> BallState {
 
     // hard-coding certain values
     next.velocity.z = 0.0;
-    next.position.z = BALL_COLLISION_RADIUS;
+    next.position.z = arena.ball_collision_radius;
 
     next
 }
 
 /// returns normal at contact point if ball is currently colliding with the arena
-pub fn arena_contact_normal(ball: &BallState) -> Option<Unit<Vector3<f32>>> {
-    let sphere = ncollide::shape::Ball::new(BALL_COLLISION_RADIUS);
+pub fn arena_contact_normal(ball: &BallState, arena: &Arena) -> Option<Unit<Vector3<f32>>> {
+    let sphere = ncollide::shape::Ball::new(arena.ball_collision_radius);
     let ball_pos = Isometry3::new(ball.position, na::zero()); // TODO if we want to handle cube ball, track and pass on the rotation
     let arena_pos = Isometry3::new(na::zero(), na::zero());
 
     let margin = 0.0;
-    let contact = ncollide::query::contact(&arena_pos, &(*ARENA), &ball_pos, &sphere, margin);
+    let contact = ncollide::query::contact(&arena_pos, &arena.mesh, &ball_pos, &sphere, margin);
 
     contact.map(|c| c.normal)
 }
 
 // FIXME lot more going in the rl utils now, probably helps handle more types of bounces:
 // https://github.com/samuelpmish/RLUtilities/blob/master/src/simulation/ball.cc#L36
-fn calculate_bounce(ball: &BallState, normal: &Unit<Vector3<f32>>) -> BallState {
+fn calculate_bounce(ball: &BallState, normal: &Unit<Vector3<f32>>, arena: &Arena) -> BallState {
     let mut bounced = (*ball).clone();
 
     let v_perp = na::Matrix::dot(&ball.velocity, &normal.into_inner()) * normal.into_inner();
     let v_para = ball.velocity - v_perp;
-    let v_spin = BALL_COLLISION_RADIUS * normal.cross(&ball.angular_velocity); // velocity of edge of ball, relative to ball center
+    let v_spin = arena.ball_collision_radius * normal.cross(&ball.angular_velocity); // velocity of edge of ball, relative to ball center
     let s = v_para + v_spin; // this is the velocity at point of impact (edge of ball) in global coords
 
     let ratio = v_perp.norm() / s.norm();
@@ -158,7 +174,7 @@ fn calculate_bounce(ball: &BallState, normal: &Unit<Vector3<f32>>) -> BallState
     let delta_v_para = -f32::min(1.0, Y * ratio) * MU * s;
 
     bounced.velocity += delta_v_perp + delta_v_para;
-    bounced.angular_velocity += A * BALL_COLLISION_RADIUS * delta_v_para.cross(&normal);
+    bounced.angular_velocity += A * arena.ball_collision_radius * delta_v_para.cross(&normal);
 
     bounced
 }
@@ -178,19 +194,26 @@ fn psyonix_scale_impulse(val: f32) -> f32 {
 /// actually *is* a collision between the ball and player, otherwise the result of this is
 /// unpredictable
 #[allow(non_snake_case)]
-pub fn calculate_hit(ball: &BallState, player: &PlayerState, collision: &Vector3<f32>) -> Result<BallState, Box<dyn Error>> {
+pub fn calculate_hit(
+    ball: &BallState,
+    player: &PlayerState,
+    collision: &Vector3<f32>,
+    arena: &Arena,
+) -> Result<BallState, PredictError> {
+    let ball_inertia = arena.ball_inertia(BALL_MASS);
+
     let n1 = (collision - ball.position).normalize();
 
     let L_b = (collision - ball.position).cross_matrix();
     let L_c = (collision - player.position).cross_matrix();
 
     let player_rotation = player.rotation.to_rotation_matrix();
-    let invI_c = player_rotation * (*CAR_INVERSE_INERTIA * player_rotation.transpose());
+    let invI_c = player_rotation * (player.hitbox.inverse_inertia() * player_rotation.transpose());
 
     let invM = ((1.0 / BALL_MASS) + (1.0 / CAR_MASS)) * na::Matrix3::identity()
-        - ((L_b * L_b) / BALL_INERTIA)
+        - ((L_b * L_b) / ball_inertia)
         - (L_c * (invI_c * L_c));
-    let M = invM.try_inverse().ok_or("M matrix inversion failed")?;
+    let M = invM.try_inverse().ok_or(PredictError::SingularMatrix)?;
 
     let delta_v = (player.velocity - (L_c * player.angular_velocity)) - (ball.velocity - (L_b * ball.angular_velocity));
 
@@ -215,7 +238,7 @@ pub fn calculate_hit(ball: &BallState, player: &PlayerState, collision: &Vector3
     let J2 = BALL_MASS * dv * psyonix_scale_impulse(dv) * n2;
 
     let mut hit_ball = ball.clone();
-    hit_ball.angular_velocity += (L_b * J1) / BALL_INERTIA;
+    hit_ball.angular_velocity += (L_b * J1) / ball_inertia;
     hit_ball.velocity += (J1 + J2) / BALL_MASS;
 
     Ok(hit_ball)
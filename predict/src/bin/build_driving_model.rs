@@ -1,35 +1,25 @@
-extern crate bincode;
-extern crate flate2;
 extern crate predict;
 extern crate state;
 
-use bincode::serialize_into;
-use flate2::write::GzEncoder;
-use flate2::Compression;
-use predict::driving_model::{DrivingModel, PlayerTransformation, TransformationMap};
+use predict::driving_model::{self, DrivingModel, PlayerTransformation, TransformationMap};
 use predict::sample;
 use state::*;
 use std::collections::hash_map::Entry::{Occupied, Vacant};
 use std::error::Error;
-use std::fs::{create_dir_all, File};
-use std::io::BufWriter;
+use std::fs::create_dir_all;
 use std::path::Path;
 
 fn build_model_for(control_branch: &str) -> DrivingModel {
     let all_samples = sample::load_all_samples(&format!("./data/samples/flat_ground/{}/", control_branch));
     let mut model = DrivingModel::default();
     index_all_samples(&mut model.tick2, &all_samples, 2);
+    index_all_samples(&mut model.tick4, &all_samples, 4);
+    index_all_samples(&mut model.tick8, &all_samples, 8);
     index_all_samples(&mut model.tick16, &all_samples, 16);
-    //index_all_samples(&mut model.tick32, &all_samples, 32);
+    index_all_samples(&mut model.tick32, &all_samples, 32);
     model
 }
 
-fn write_model(path: &Path, model: DrivingModel) -> Result<(), Box<dyn Error>> {
-    let f = BufWriter::new(File::create(path)?);
-    let mut e = GzEncoder::new(f, Compression::default());
-    Ok(serialize_into(&mut e, &model)?)
-}
-
 fn index_all_samples(indexed: &mut TransformationMap, all_samples: &[Vec<PlayerState>], num_ticks: usize) {
     for sample in all_samples {
         if sample.len() < sample::MIN_SAMPLE_LENGTH {
@@ -112,8 +102,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     create_dir_all(&base_path)?;
     for control_branch in control_branches.iter() {
         let model = build_model_for(control_branch);
-        let filename = format!("{}.bincode.gz", control_branch);
-        write_model(&base_path.join(filename), model)?;
+        model.save(&driving_model::model_path(base_path, control_branch))?;
     }
 
     Ok(())
@@ -1,86 +1,220 @@
-use bincode::deserialize_from;
+use bincode::{deserialize_from, serialize_into};
 
 use fnv::FnvHasher;
 use std::collections::HashMap;
 use std::error::Error;
 use std::hash::BuildHasherDefault;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
 
 use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use std::fs::File;
-use std::io::BufReader;
+use std::io::{BufReader, BufWriter};
 
+use error::PredictError;
 use sample;
 use state::*;
 
 type MyHasher = BuildHasherDefault<FnvHasher>;
 
+/// the discrete controller combinations we model, named after their model files
+pub const CONTROLLER_VARIANTS: [&str; 18] = [
+    "throttle_right",
+    "boost_right",
+    "idle_right",
+    "reverse_right",
+    "throttle_right_drift",
+    "boost_right_drift",
+    "throttle_left",
+    "boost_left",
+    "idle_left",
+    "reverse_left",
+    "throttle_left_drift",
+    "boost_left_drift",
+    "throttle_straight",
+    "boost_straight",
+    "idle_straight",
+    "reverse_straight",
+    "throttle_straight_drift",
+    "boost_straight_drift",
+];
+
+const DEFAULT_MODEL_DIR: &str = "./models/flat_ground";
+
 lazy_static! {
-    static ref THROTTLE_RIGHT: DrivingModel =
-        DrivingModel::load("models/flat_ground/throttle_right.bincode.gz").expect("Failed to load driving model");
-    static ref BOOST_RIGHT: DrivingModel =
-        DrivingModel::load("models/flat_ground/boost_right.bincode.gz").expect("Failed to load driving model");
-    static ref IDLE_RIGHT: DrivingModel =
-        DrivingModel::load("models/flat_ground/idle_right.bincode.gz").expect("Failed to load driving model");
-    static ref REVERSE_RIGHT: DrivingModel =
-        DrivingModel::load("models/flat_ground/reverse_right.bincode.gz").expect("Failed to load driving model");
-    static ref THROTTLE_RIGHT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/throttle_right_drift.bincode.gz").expect("Failed to load driving model");
-    static ref BOOST_RIGHT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/boost_right_drift.bincode.gz").expect("Failed to load driving model");
-    static ref IDLE_RIGHT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/idle_right_drift.bincode.gz").expect("Failed to load driving model");
-    static ref REVERSE_RIGHT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/reverse_right_drift.bincode.gz").expect("Failed to load driving model");
-    static ref THROTTLE_LEFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/throttle_left.bincode.gz").expect("Failed to load driving model");
-    static ref BOOST_LEFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/boost_left.bincode.gz").expect("Failed to load driving model");
-    static ref IDLE_LEFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/idle_left.bincode.gz").expect("Failed to load driving model");
-    static ref REVERSE_LEFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/reverse_left.bincode.gz").expect("Failed to load driving model");
-    static ref THROTTLE_LEFT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/throttle_left_drift.bincode.gz").expect("Failed to load driving model");
-    static ref BOOST_LEFT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/boost_left_drift.bincode.gz").expect("Failed to load driving model");
-    static ref IDLE_LEFT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/idle_left_drift.bincode.gz").expect("Failed to load driving model");
-    static ref REVERSE_LEFT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/reverse_left_drift.bincode.gz").expect("Failed to load driving model");
-    static ref THROTTLE_STRAIGHT: DrivingModel =
-        DrivingModel::load("models/flat_ground/throttle_straight.bincode.gz").expect("Failed to load driving model");
-    static ref BOOST_STRAIGHT: DrivingModel =
-        DrivingModel::load("models/flat_ground/boost_straight.bincode.gz").expect("Failed to load driving model");
-    static ref IDLE_STRAIGHT: DrivingModel =
-        DrivingModel::load("models/flat_ground/idle_straight.bincode.gz").expect("Failed to load driving model");
-    static ref REVERSE_STRAIGHT: DrivingModel =
-        DrivingModel::load("models/flat_ground/reverse_straight.bincode.gz").expect("Failed to load driving model");
-    static ref THROTTLE_STRAIGHT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/throttle_straight_drift.bincode.gz").expect("Failed to load driving model");
-    static ref BOOST_STRAIGHT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/boost_straight_drift.bincode.gz").expect("Failed to load driving model");
-    static ref IDLE_STRAIGHT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/idle_straight_drift.bincode.gz").expect("Failed to load driving model");
-    static ref REVERSE_STRAIGHT_DRIFT: DrivingModel =
-        DrivingModel::load("models/flat_ground/reverse_straight_drift.bincode.gz").expect("Failed to load driving model");
+    static ref MODEL_DIR: RwLock<PathBuf> = RwLock::new(PathBuf::from(DEFAULT_MODEL_DIR));
+    /// car bodies with their own models, instead of the ones in MODEL_DIR
+    static ref BODY_MODEL_DIRS: RwLock<HashMap<Hitbox, PathBuf>> = RwLock::new(HashMap::new());
+    /// by directory, so bodies sharing a directory share the loaded models too. failures are kept
+    /// so that we don't retry loading for every prediction
+    static ref MODEL_SETS: RwLock<HashMap<PathBuf, Result<Arc<DrivingModelSet>, PredictError>>> =
+        RwLock::new(HashMap::new());
+}
+
+/// where to load driving models from, for any car body without its own directory
+pub fn set_model_dir<P: AsRef<Path>>(dir: P) {
+    *MODEL_DIR.write().expect("Model dir lock poisoned") = dir.as_ref().to_owned();
+}
+
+/// where to load driving models from for the given car body
+pub fn set_body_model_dir<P: AsRef<Path>>(hitbox: Hitbox, dir: P) {
+    BODY_MODEL_DIRS
+        .write()
+        .expect("Body model dirs lock poisoned")
+        .insert(hitbox, dir.as_ref().to_owned());
+}
+
+/// the driving models for the given car body, loaded the first time they're needed
+pub fn models_for(hitbox: Hitbox) -> Result<Arc<DrivingModelSet>, PredictError> {
+    let dir = match BODY_MODEL_DIRS.read().expect("Body model dirs lock poisoned").get(&hitbox) {
+        Some(dir) => dir.clone(),
+        None => MODEL_DIR.read().expect("Model dir lock poisoned").clone(),
+    };
+
+    if let Some(models) = MODEL_SETS.read().expect("Model sets lock poisoned").get(&dir) {
+        return models.clone();
+    }
+
+    let models = DrivingModelSet::load(&dir).map(Arc::new);
+    match models {
+        Ok(ref models) if !models.missing().is_empty() => eprintln!(
+            "Warning: driving models missing from {}: {}",
+            dir.display(),
+            models.missing().join(", ")
+        ),
+        Err(ref e) => eprintln!("Error: {}", e),
+        _ => {}
+    }
+
+    MODEL_SETS
+        .write()
+        .expect("Model sets lock poisoned")
+        .entry(dir)
+        .or_insert(models)
+        .clone()
 }
 
+pub fn model_path(dir: &Path, variant: &str) -> PathBuf {
+    dir.join(format!("{}.bincode.gz", variant))
+}
+
+/// where the tables added after the original model file format are saved, see DrivingModel
+fn extra_tables_path(model_path: &Path) -> PathBuf {
+    let file_name = model_path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    let variant = file_name.trim_end_matches(".bincode.gz");
+    model_path.with_file_name(format!("{}.extra.bincode.gz", variant))
+}
+
+/// the driving models for every controller variant found in a directory
+pub struct DrivingModelSet {
+    models: HashMap<&'static str, DrivingModel, MyHasher>,
+    missing: Vec<&'static str>,
+}
+
+impl DrivingModelSet {
+    /// variants without a model file are left out and listed in missing(). it's only an error if
+    /// there are no models at all, or if a model file fails to load
+    pub fn load(dir: &Path) -> Result<DrivingModelSet, PredictError> {
+        let mut models = HashMap::default();
+        let mut missing = vec![];
+        for &variant in CONTROLLER_VARIANTS.iter() {
+            let path = model_path(dir, variant);
+            if !path.exists() {
+                missing.push(variant);
+                continue;
+            }
+
+            let model = DrivingModel::load(&path.to_string_lossy()).map_err(|e| PredictError::ModelLoad {
+                path: path.display().to_string(),
+                error: e.to_string(),
+            })?;
+            models.insert(variant, model);
+        }
+
+        if models.is_empty() {
+            return Err(PredictError::ModelLoad {
+                path: dir.display().to_string(),
+                error: "no driving models found".to_owned(),
+            });
+        }
+
+        Ok(DrivingModelSet { models, missing })
+    }
+
+    pub fn get(&self, variant: &str) -> Option<&DrivingModel> {
+        self.models.get(variant)
+    }
+
+    /// controller variants we have no model for
+    pub fn missing(&self) -> &[&'static str] {
+        &self.missing
+    }
+}
+
+/// the step sizes we have tables for, largest first. other step sizes are composed out of these
+pub const TABLE_TICKS: [usize; 5] = [32, 16, 8, 4, 2];
+
 pub type TransformationMap = HashMap<sample::NormalizedPlayerState, PlayerTransformation, MyHasher>;
 
-#[derive(Serialize, Deserialize, Default)]
+/// a table per step duration the search can use. the 16 and 2-tick tables are saved in the
+/// original model file format, and the newer tables alongside it in a separate file, so that
+/// models built before those tables existed still load
+#[derive(Default)]
 pub struct DrivingModel {
-    //pub tick32: TransformationMap,
+    pub tick32: TransformationMap,
     pub tick16: TransformationMap,
-    //pub tick8: TransformationMap,
-    //pub tick4: TransformationMap,
+    pub tick8: TransformationMap,
+    pub tick4: TransformationMap,
     pub tick2: TransformationMap,
 }
 
 impl DrivingModel {
+    /// the newer tables are left empty if the model predates them
     pub fn load(path: &str) -> Result<DrivingModel, Box<dyn Error>> {
         let f = BufReader::new(File::open(path)?);
         let mut decoder = GzDecoder::new(f);
-        Ok(deserialize_from(&mut decoder)?)
+        // NOTE bincode encodes a struct the same as a tuple of its fields
+        let (tick16, tick2) = deserialize_from(&mut decoder)?;
+
+        let extra_path = extra_tables_path(Path::new(path));
+        let (tick32, tick8, tick4) = if extra_path.exists() {
+            let f = BufReader::new(File::open(extra_path)?);
+            let mut decoder = GzDecoder::new(f);
+            deserialize_from(&mut decoder)?
+        } else {
+            Default::default()
+        };
+
+        Ok(DrivingModel {
+            tick32,
+            tick16,
+            tick8,
+            tick4,
+            tick2,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let f = BufWriter::new(File::create(path)?);
+        let mut encoder = GzEncoder::new(f, Compression::default());
+        serialize_into(&mut encoder, &(&self.tick16, &self.tick2))?;
+
+        let f = BufWriter::new(File::create(extra_tables_path(path))?);
+        let mut encoder = GzEncoder::new(f, Compression::default());
+        Ok(serialize_into(&mut encoder, &(&self.tick32, &self.tick8, &self.tick4))?)
+    }
+
+    pub fn table(&self, num_ticks: usize) -> Option<&TransformationMap> {
+        match num_ticks {
+            32 => Some(&self.tick32),
+            16 => Some(&self.tick16),
+            8 => Some(&self.tick8),
+            4 => Some(&self.tick4),
+            2 => Some(&self.tick2),
+            _ => None,
+        }
     }
 }
 
@@ -128,51 +262,60 @@ impl PlayerTransformation {
     }
 }
 
-pub(crate) fn get_relevant_transformation(
-    normalized: sample::NormalizedPlayerState,
-    controller: &BrickControllerState,
-    time_step: f32,
-) -> Option<&'static PlayerTransformation> {
+/// the driving model for the controller. its model may not have been built, eg we only record
+/// forward throttle so far, in which case predicting with it fails with MissingModel
+pub fn controller_variant(controller: &BrickControllerState) -> Result<&'static str, PredictError> {
     #[rustfmt::skip]
-    let driving_model: &DrivingModel = match (
+    let variant = match (
         &controller.steer,
         &controller.throttle,
         controller.boost,
         controller.handbrake,
     ) {
-        (&Steer::Right   , &Throttle::Forward, false, false) => &THROTTLE_RIGHT,
-        (&Steer::Right   , _                 , true , false) => &BOOST_RIGHT, // TODO confirm braking plus boosting is same as boosting
-        (&Steer::Right   , &Throttle::Idle   , false, false) => &IDLE_RIGHT,
-        (&Steer::Right   , &Throttle::Reverse, false, false) => &REVERSE_RIGHT,
-        (&Steer::Right   , &Throttle::Forward, false, true ) => &THROTTLE_RIGHT_DRIFT,
-        (&Steer::Right   , &Throttle::Forward, true , true ) => &BOOST_RIGHT_DRIFT,
-
-        (&Steer::Left    , &Throttle::Forward, false, false) => &THROTTLE_LEFT,
-        (&Steer::Left    , _                 , true , false) => &BOOST_LEFT,
-        (&Steer::Left    , &Throttle::Idle   , false, false) => &IDLE_LEFT,
-        (&Steer::Left    , &Throttle::Reverse, false, false) => &REVERSE_LEFT,
-        (&Steer::Left    , &Throttle::Forward, false, true ) => &THROTTLE_LEFT_DRIFT,
-        (&Steer::Left    , _                 , true , true ) => &BOOST_LEFT_DRIFT,
-
-        (&Steer::Straight, &Throttle::Forward, false, false) => &THROTTLE_STRAIGHT,
-        (&Steer::Straight, _                 , true , false) => &BOOST_STRAIGHT,
-        (&Steer::Straight, &Throttle::Idle   , false, false) => &IDLE_STRAIGHT,
-        (&Steer::Straight, &Throttle::Reverse, false, false) => &REVERSE_STRAIGHT,
-        (&Steer::Straight, &Throttle::Forward, false, true ) => &THROTTLE_STRAIGHT_DRIFT,
-        (&Steer::Straight, _                 , true , true ) => &BOOST_STRAIGHT_DRIFT,
+        (&Steer::Right   , &Throttle::Forward, false, false) => "throttle_right",
+        (&Steer::Right   , _                 , true , false) => "boost_right", // TODO confirm braking plus boosting is same as boosting
+        (&Steer::Right   , &Throttle::Idle   , false, false) => "idle_right",
+        (&Steer::Right   , &Throttle::Reverse, false, false) => "reverse_right",
+        (&Steer::Right   , &Throttle::Forward, false, true ) => "throttle_right_drift",
+        (&Steer::Right   , &Throttle::Forward, true , true ) => "boost_right_drift",
+
+        (&Steer::Left    , &Throttle::Forward, false, false) => "throttle_left",
+        (&Steer::Left    , _                 , true , false) => "boost_left",
+        (&Steer::Left    , &Throttle::Idle   , false, false) => "idle_left",
+        (&Steer::Left    , &Throttle::Reverse, false, false) => "reverse_left",
+        (&Steer::Left    , &Throttle::Forward, false, true ) => "throttle_left_drift",
+        (&Steer::Left    , _                 , true , true ) => "boost_left_drift",
+
+        (&Steer::Straight, &Throttle::Forward, false, false) => "throttle_straight",
+        (&Steer::Straight, _                 , true , false) => "boost_straight",
+        (&Steer::Straight, &Throttle::Idle   , false, false) => "idle_straight",
+        (&Steer::Straight, &Throttle::Reverse, false, false) => "reverse_straight",
+        (&Steer::Straight, &Throttle::Forward, false, true ) => "throttle_straight_drift",
+        (&Steer::Straight, _                 , true , true ) => "boost_straight_drift",
+
+        // there are no models for these, player prediction interpolates between the discrete ones
+        (&Steer::Analog(_), _            , _    , _    ) => return Err(PredictError::UnsupportedController(controller.clone())),
+        (_      , &Throttle::Analog(_)   , _    , _    ) => return Err(PredictError::UnsupportedController(controller.clone())),
 
         // ignoring the other drift variants (idle/reverse) for now
-        (_               , _                 , _    , true ) => unimplemented!(),
+        (_               , _                 , _    , true ) => return Err(PredictError::UnsupportedController(controller.clone())),
     };
+    Ok(variant)
+}
 
-    // TODO use const fn + match when possible: https://github.com/rust-lang/rust/issues/57240
-    if time_step == 16.0 * TICK {
-        driving_model.tick16.get(&normalized)
-    //} else if time_step == 32.0 * TICK {
-    //    driving_model.tick32.get(&normalized)
-    } else if time_step == 2.0 * TICK {
-        driving_model.tick2.get(&normalized)
-    } else {
-        panic!(format!("Don't know how to model time step: {}", time_step));
-    }
+pub(crate) fn get_relevant_transformation<'a>(
+    models: &'a DrivingModelSet,
+    normalized: sample::NormalizedPlayerState,
+    controller: &BrickControllerState,
+    time_step: f32,
+) -> Result<Option<&'a PlayerTransformation>, PredictError> {
+    let variant = controller_variant(controller)?;
+    let driving_model = models.get(variant).ok_or(PredictError::MissingModel(variant))?;
+
+    // callers compose other step sizes out of TABLE_TICKS
+    let num_ticks = (time_step / TICK).round() as usize;
+    let table = driving_model
+        .table(num_ticks)
+        .ok_or(PredictError::UnsupportedTimeStep(time_step))?;
+    Ok(table.get(&normalized))
 }
@@ -0,0 +1,49 @@
+use sample::NormalizedPlayerState;
+use state::BrickControllerState;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone)]
+pub enum PredictError {
+    /// no driving model sample near enough to the player state to predict from
+    MissingTransformation {
+        normalized: NormalizedPlayerState,
+        controller: BrickControllerState,
+    },
+    /// not a whole number of ticks, or no driving model table for it
+    UnsupportedTimeStep(f32),
+    /// no driving model for this combination of inputs, eg drifting while idle
+    UnsupportedController(BrickControllerState),
+    /// the driving model for this controller variant wasn't built, see DrivingModelSet::missing
+    MissingModel(&'static str),
+    ModelLoad {
+        path: String,
+        error: String,
+    },
+    ArenaLoad {
+        path: String,
+        error: String,
+    },
+    /// the collision impulse can't be calculated
+    SingularMatrix,
+}
+
+impl fmt::Display for PredictError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PredictError::MissingTransformation { normalized, controller } => write!(
+                f,
+                "Missing transformation for player: {:?} & controller: {:?}",
+                normalized, controller
+            ),
+            PredictError::UnsupportedTimeStep(time_step) => write!(f, "Can't model time step: {}", time_step),
+            PredictError::UnsupportedController(controller) => write!(f, "Can't model controller: {:?}", controller),
+            PredictError::MissingModel(variant) => write!(f, "Missing driving model: {}", variant),
+            PredictError::ModelLoad { path, error } => write!(f, "Failed to load driving model {}: {}", path, error),
+            PredictError::ArenaLoad { path, error } => write!(f, "Failed to load arena mesh {}: {}", path, error),
+            PredictError::SingularMatrix => write!(f, "M matrix inversion failed"),
+        }
+    }
+}
+
+impl Error for PredictError {}
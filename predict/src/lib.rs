@@ -17,5 +17,8 @@ extern crate bincode;
 pub mod arena;
 pub mod ball;
 pub mod driving_model;
+pub mod error;
 pub mod player;
 pub mod sample;
+
+pub use error::PredictError;
@@ -1,4 +1,5 @@
 use driving_model;
+use error::PredictError;
 use na::{Rotation3, UnitQuaternion, Vector3};
 use sample;
 use state::*;
@@ -8,6 +9,8 @@ use std::f32;
 pub enum PredictionCategory {
     /// Wheels on ground
     Ground,
+    /// Wheels not touching arena
+    Air,
     /* TODO
     /// Top/Sides on ground
     Ground2,
@@ -17,14 +20,16 @@ pub enum PredictionCategory {
     Ceiling,
     /// Wheels on curve. might want to expand this into side/back/top/bottom/corner/etc curves
     CurveWall,
-    /// Wheels not touching arena
-    Air,
     */
 }
 
-pub fn find_prediction_category(_current: &PlayerState) -> PredictionCategory {
-    // hard-coded the only thing we can handle right now
-    PredictionCategory::Ground
+pub fn find_prediction_category(current: &PlayerState) -> PredictionCategory {
+    // TODO distinguish walls/ceiling/curves, which also have wheel contact
+    if current.on_ground {
+        PredictionCategory::Ground
+    } else {
+        PredictionCategory::Air
+    }
 }
 
 /// for now, doesn't handle landing sideways or at any angle really, nor drifting. collision with
@@ -33,10 +38,11 @@ fn next_player_state_grounded(
     current: &PlayerState,
     controller: &BrickControllerState,
     time_step: f32,
-) -> Result<PlayerState, String> {
+) -> Result<PlayerState, PredictError> {
     let mut next = (*current).clone();
 
-    let (translation, velocity, angular_velocity, rotation) = ground_turn_prediction(&current, &controller, time_step)?;
+    let (translation, velocity, angular_velocity, rotation) =
+        analog_ground_turn_prediction(&current, &controller, time_step)?;
 
     // because we extrapolate around the edges of our measurements, it's possible we calculate
     // a velocity beyond what's possible in the game. so we must scale it down here.
@@ -49,15 +55,21 @@ fn next_player_state_grounded(
     // XXX NOTE using the velocity scaling for the translation isn't correct at all, but it's
     // likely to be at least proportional, and likely closer to correct than not scaling at all.
     next.position = current.position + scale * translation;
-    next.position.z = RESTING_Z; // avoid drifting upward/downward when we're just driving on the ground!
+    next.position.z = current.hitbox.resting_z(); // avoid drifting upward/downward when we're just driving on the ground!
     next.velocity = scale * velocity;
     next.angular_velocity = angular_velocity;
     next.rotation = UnitQuaternion::from_rotation_matrix(&rotation); // was easier to just return the end rotation directly. TODO stop using quaternion
 
+    // landing resets the jumps
+    next.on_ground = true;
+    next.jumped = false;
+    next.double_jumped = false;
+
     Ok(next)
 }
 
-fn ground_turn_matching_transformation(
+fn ground_turn_matching_transformation<'a>(
+    models: &'a driving_model::DrivingModelSet,
     normalized: sample::NormalizedPlayerState,
     controller: &BrickControllerState,
     time_step: f32,
@@ -65,11 +77,11 @@ fn ground_turn_matching_transformation(
     yrange: i16,
     skipx: Option<i16>,
     skipy: Option<i16>,
-) -> Option<&'static driving_model::PlayerTransformation> {
+) -> Result<Option<&'a driving_model::PlayerTransformation>, PredictError> {
     // based on current player state, and steer, throttle and boost, gets the right transformation,
     // with some wiggle room based on xrange/yrange
     let mut local_normalized = normalized;
-    let mut transformation: Option<&'static driving_model::PlayerTransformation> = None;
+    let mut transformation: Option<&'a driving_model::PlayerTransformation> = None;
 
     // step_by is not yet stabilized... so using plain loops instead
     let ystep = if yrange < 0 { -1 } else { 1 };
@@ -83,7 +95,8 @@ fn ground_turn_matching_transformation(
                 local_normalized.local_vx = normalized.local_vx + dx;
                 //println!("local_normalized: {:?}", local_normalized);
 
-                transformation = driving_model::get_relevant_transformation(local_normalized, &controller, time_step);
+                transformation =
+                    driving_model::get_relevant_transformation(models, local_normalized, &controller, time_step)?;
                 if transformation.is_some() {
                     break 'outer;
                 }
@@ -101,7 +114,7 @@ fn ground_turn_matching_transformation(
         }
     }
 
-    transformation
+    Ok(transformation)
 }
 
 fn interpolate_transformation_halfway(
@@ -136,20 +149,21 @@ fn interpolate_transformation_halfway(
     transformation
 }
 
-fn ground_turn_quad_tranformations(
+fn ground_turn_quad_tranformations<'a>(
+    models: &'a driving_model::DrivingModelSet,
     current: &PlayerState,
     controller: &BrickControllerState,
     time_step: f32,
-) -> [Option<&'static driving_model::PlayerTransformation>; 4] {
+) -> Result<[Option<&'a driving_model::PlayerTransformation>; 4], PredictError> {
     // TODO handle missing values properly: go lower/higher to find another point to use as an
     // interpolation anchor
     //println!("x1y1");
     let normalized = sample::normalized_player(&current, false, false);
-    let mut x1y1 = ground_turn_matching_transformation(normalized, &controller, time_step, -3, -3, None, None);
+    let mut x1y1 = ground_turn_matching_transformation(models, normalized, &controller, time_step, -3, -3, None, None)?;
 
     //println!("x2y1");
     let normalized = sample::normalized_player(&current, true, false);
-    let mut x2y1 = ground_turn_matching_transformation(normalized, &controller, time_step, 3, -3, None, None);
+    let mut x2y1 = ground_turn_matching_transformation(models, normalized, &controller, time_step, 3, -3, None, None)?;
 
     // when we fail in on direction, search in the other
     if x1y1.is_some() && x2y1.is_none() {
@@ -157,6 +171,7 @@ fn ground_turn_quad_tranformations(
         let x1y1_transformation = x1y1.as_ref().unwrap();
         let skip = x1y1_transformation.normalized_player(current.angular_velocity.z);
         x2y1 = ground_turn_matching_transformation(
+            models,
             normalized,
             &controller,
             time_step,
@@ -164,12 +179,13 @@ fn ground_turn_quad_tranformations(
             -3,
             Some(skip.local_vx),
             Some(skip.local_vy),
-        );
+        )?;
     } else if x2y1.is_some() && x1y1.is_none() {
         //println!("-- x1y1 fallback --");
         let x2y1_transformation = x2y1.as_ref().unwrap();
         let skip = x2y1_transformation.normalized_player(current.angular_velocity.z);
         x1y1 = ground_turn_matching_transformation(
+            models,
             normalized,
             &controller,
             time_step,
@@ -177,18 +193,18 @@ fn ground_turn_quad_tranformations(
             -3,
             Some(skip.local_vx),
             Some(skip.local_vy),
-        );
+        )?;
     } else if x2y1.is_none() && x1y1.is_none() {
         //println!("-- BOTH FAILED --");
     }
 
     //println!("x1y2");
     let normalized = sample::normalized_player(&current, false, true);
-    let mut x1y2 = ground_turn_matching_transformation(normalized, &controller, time_step, -3, 3, None, None);
+    let mut x1y2 = ground_turn_matching_transformation(models, normalized, &controller, time_step, -3, 3, None, None)?;
 
     //println!("x2y2");
     let normalized = sample::normalized_player(&current, true, true);
-    let mut x2y2 = ground_turn_matching_transformation(normalized, &controller, time_step, 3, 3, None, None);
+    let mut x2y2 = ground_turn_matching_transformation(models, normalized, &controller, time_step, 3, 3, None, None)?;
 
     // when we fail in on direction, search in the other
     if x1y2.is_some() && x2y2.is_none() {
@@ -196,6 +212,7 @@ fn ground_turn_quad_tranformations(
         let x1y2_transformation = x1y2.as_ref().unwrap();
         let skip = x1y2_transformation.normalized_player(current.angular_velocity.z);
         x2y2 = ground_turn_matching_transformation(
+            models,
             normalized,
             &controller,
             time_step,
@@ -203,12 +220,13 @@ fn ground_turn_quad_tranformations(
             3,
             Some(skip.local_vx),
             Some(skip.local_vy),
-        );
+        )?;
     } else if x2y2.is_some() && x1y2.is_none() {
         //println!("-- x1y2 fallback --");
         let x2y2_transformation = x2y2.as_ref().unwrap();
         let skip = x2y2_transformation.normalized_player(current.angular_velocity.z);
         x1y2 = ground_turn_matching_transformation(
+            models,
             normalized,
             &controller,
             time_step,
@@ -216,12 +234,12 @@ fn ground_turn_quad_tranformations(
             3,
             Some(skip.local_vx),
             Some(skip.local_vy),
-        );
+        )?;
     } else if x2y2.is_none() && x1y2.is_none() {
         //println!("-- BOTH FAILED 2 --");
     }
 
-    [x1y1, x2y1, x1y2, x2y2]
+    Ok([x1y1, x2y1, x1y2, x2y2])
 }
 
 /// tuple of (translation, acceleration, angular_acceleration, rotation)
@@ -231,42 +249,30 @@ fn ground_turn_prediction(
     current: &PlayerState,
     controller: &BrickControllerState,
     time_step: f32,
-) -> Result<PlayerPrediction, String> {
+) -> Result<PlayerPrediction, PredictError> {
     // we don't have transformations for single ticks, but we'll do some special handling of
     // this case as we need it for car-ball collisions
     let original_time_step = time_step;
     let time_step = if time_step == TICK { 2.0 * TICK } else { time_step };
 
-    let quad = ground_turn_quad_tranformations(current, controller, time_step);
+    let models = driving_model::models_for(current.hitbox)?;
+    let quad = ground_turn_quad_tranformations(&models, current, controller, time_step)?;
 
-    // TODO error
-    let mut x1y1 = quad[0].ok_or_else(|| {
-        format!(
-            "Missing turn x1y1 for player: {:?} & controller: {:?}",
-            sample::normalized_player(&current, false, false),
-            controller
-        )
+    let mut x1y1 = quad[0].ok_or_else(|| PredictError::MissingTransformation {
+        normalized: sample::normalized_player(&current, false, false),
+        controller: controller.clone(),
     })?;
-    let mut x2y1 = quad[1].ok_or_else(|| {
-        format!(
-            "Missing turn x2y1 for player: {:?} & controller: {:?}",
-            sample::normalized_player(&current, true, false),
-            controller
-        )
+    let mut x2y1 = quad[1].ok_or_else(|| PredictError::MissingTransformation {
+        normalized: sample::normalized_player(&current, true, false),
+        controller: controller.clone(),
     })?;
-    let mut x1y2 = quad[2].ok_or_else(|| {
-        format!(
-            "Missing turn x1y2 for player: {:?} & controller: {:?}",
-            sample::normalized_player(&current, false, true),
-            controller
-        )
+    let mut x1y2 = quad[2].ok_or_else(|| PredictError::MissingTransformation {
+        normalized: sample::normalized_player(&current, false, true),
+        controller: controller.clone(),
     })?;
-    let mut x2y2 = quad[3].ok_or_else(|| {
-        format!(
-            "Missing turn x2y2 for player: {:?} & controller: {:?}",
-            sample::normalized_player(&current, true, true),
-            controller
-        )
+    let mut x2y2 = quad[3].ok_or_else(|| PredictError::MissingTransformation {
+        normalized: sample::normalized_player(&current, true, true),
+        controller: controller.clone(),
     })?;
 
     // interpolating for a single tick, for which we are lacking data currently
@@ -352,6 +358,77 @@ fn ground_turn_prediction(
     ))
 }
 
+/// the discrete steer values on either side of the given one, and how far it is between them
+fn steer_bounds(steer: Steer) -> (Steer, Steer, f32) {
+    match steer {
+        Steer::Analog(value) if value > 0.0 => (Steer::Straight, Steer::Right, value),
+        Steer::Analog(value) => (Steer::Straight, Steer::Left, -value),
+        _ => (steer, steer, 0.0),
+    }
+}
+
+fn throttle_bounds(throttle: Throttle) -> (Throttle, Throttle, f32) {
+    match throttle {
+        Throttle::Analog(value) if value > 0.0 => (Throttle::Idle, Throttle::Forward, value),
+        Throttle::Analog(value) => (Throttle::Idle, Throttle::Reverse, -value),
+        _ => (throttle, throttle, 0.0),
+    }
+}
+
+/// handles analog steer and throttle by predicting with the discrete values on either side and
+/// interpolating the results.
+// NOTE this assumes the car's response is linear in the input, which is roughly true for the turn
+// radius and acceleration, but isn't exact
+fn analog_ground_turn_prediction(
+    current: &PlayerState,
+    controller: &BrickControllerState,
+    time_step: f32,
+) -> Result<PlayerPrediction, PredictError> {
+    let (steer1, steer2, steer_factor) = steer_bounds(controller.steer);
+    if steer1 != steer2 {
+        let controller1 = BrickControllerState {
+            steer: steer1,
+            ..controller.clone()
+        };
+        let controller2 = BrickControllerState {
+            steer: steer2,
+            ..controller.clone()
+        };
+        let prediction1 = analog_ground_turn_prediction(current, &controller1, time_step)?;
+        let prediction2 = analog_ground_turn_prediction(current, &controller2, time_step)?;
+        return Ok(interpolate_prediction(prediction1, prediction2, steer_factor));
+    }
+
+    // throttle doesn't matter when boosting
+    let (throttle1, throttle2, throttle_factor) = throttle_bounds(controller.throttle);
+    if throttle1 != throttle2 && !controller.boost {
+        let controller1 = BrickControllerState {
+            throttle: throttle1,
+            ..controller.clone()
+        };
+        let controller2 = BrickControllerState {
+            throttle: throttle2,
+            ..controller.clone()
+        };
+        let prediction1 = ground_turn_prediction(current, &controller1, time_step)?;
+        let prediction2 = ground_turn_prediction(current, &controller2, time_step)?;
+        return Ok(interpolate_prediction(prediction1, prediction2, throttle_factor));
+    }
+
+    ground_turn_prediction(current, controller, time_step)
+}
+
+fn interpolate_prediction(start: PlayerPrediction, end: PlayerPrediction, factor: f32) -> PlayerPrediction {
+    let start_rotation = UnitQuaternion::from_rotation_matrix(&start.3);
+    let end_rotation = UnitQuaternion::from_rotation_matrix(&end.3);
+    (
+        interpolate(start.0, end.0, factor),
+        interpolate(start.1, end.1, factor),
+        interpolate(start.2, end.2, factor),
+        start_rotation.slerp(&end_rotation, factor).to_rotation_matrix(),
+    )
+}
+
 /// factor: number from 0.0 to 1.0 for interpolation between start and end, 0.0 being 100% at
 /// start, 1.0 being 100% at end. Note that this actually also handles factors outside the 0.0 to
 /// 1.0 range, in which case it's a linear extrapolation
@@ -366,9 +443,10 @@ fn interpolate_scalar(start: f32, end: f32, factor: f32) -> f32 {
 // rip-off of: https://github.com/samuelpmish/RLUtilities/blob/master/src/simulation/ball.cc#L82
 pub fn closest_point_for_collision(ball: &BallState, player: &PlayerState) -> Vector3<f32> {
     let mut local_pos = player.rotation.to_rotation_matrix().inverse() * (ball.position - player.hitbox_center());
-    local_pos.x = na::clamp(local_pos.x, -CAR_DIMENSIONS.x / 2.0, CAR_DIMENSIONS.x / 2.0);
-    local_pos.y = na::clamp(local_pos.y, -CAR_DIMENSIONS.y / 2.0, CAR_DIMENSIONS.y / 2.0);
-    local_pos.z = na::clamp(local_pos.z, -CAR_DIMENSIONS.z / 2.0, CAR_DIMENSIONS.z / 2.0);
+    let half_dimensions = player.hitbox.dimensions() / 2.0;
+    local_pos.x = na::clamp(local_pos.x, -half_dimensions.x, half_dimensions.x);
+    local_pos.y = na::clamp(local_pos.y, -half_dimensions.y, half_dimensions.y);
+    local_pos.z = na::clamp(local_pos.z, -half_dimensions.z, half_dimensions.z);
     player.hitbox_center() + player.rotation.to_rotation_matrix() * local_pos
 }
 
@@ -376,22 +454,101 @@ pub fn ball_collides(ball: &BallState, player: &PlayerState) -> bool {
     (closest_point_for_collision(ball, player) - ball.position).norm() < BALL_COLLISION_RADIUS
 }
 
+/// predicts any whole number of ticks ahead, by composing the steps we have driving model tables
+/// for, eg 24 ticks is a 16-tick step followed by an 8-tick step. when a table has nothing near
+/// enough, we fall back to two steps of half the size
 pub fn next_player_state(
     current: &PlayerState,
     controller: &BrickControllerState,
     time_step: f32,
-) -> Result<PlayerState, String> {
+) -> Result<PlayerState, PredictError> {
+    let ticks = time_step / TICK;
+    if ticks.round() < 1.0 || (ticks - ticks.round()).abs() > 0.01 {
+        return Err(PredictError::UnsupportedTimeStep(time_step));
+    }
+
+    let mut remaining_ticks = ticks.round() as usize;
+    let mut next_player = current.clone();
+    while remaining_ticks > 0 {
+        let step_ticks = largest_step_ticks(remaining_ticks);
+        next_player = next_player_state_halving(&next_player, controller, step_ticks)?;
+        remaining_ticks -= step_ticks;
+    }
+
+    Ok(next_player)
+}
+
+/// the larger tables are sparser, since they need longer samples, eg the 32-tick table is empty
+/// for models built from 17-tick recordings
+fn next_player_state_halving(
+    current: &PlayerState,
+    controller: &BrickControllerState,
+    step_ticks: usize,
+) -> Result<PlayerState, PredictError> {
+    match next_player_state_step(current, controller, step_ticks as f32 * TICK) {
+        Err(PredictError::MissingTransformation { .. }) if step_ticks > 2 => {
+            let halfway = next_player_state_halving(current, controller, step_ticks / 2)?;
+            next_player_state_halving(&halfway, controller, step_ticks / 2)
+        }
+        result => result,
+    }
+}
+
+/// the largest step we have a table for that doesn't go past the given number of ticks. single
+/// ticks are interpolated from the 2-tick table
+fn largest_step_ticks(remaining_ticks: usize) -> usize {
+    driving_model::TABLE_TICKS
+        .iter()
+        .cloned()
+        .find(|&ticks| ticks <= remaining_ticks)
+        .unwrap_or(1)
+}
+
+fn next_player_state_step(
+    current: &PlayerState,
+    controller: &BrickControllerState,
+    time_step: f32,
+) -> Result<PlayerState, PredictError> {
+    // trying to boost without any boost left is no different from just throttling
+    let boostless_controller;
+    let controller = if controller.boost && current.boost <= 0.0 {
+        boostless_controller = BrickControllerState {
+            boost: false,
+            throttle: Throttle::Forward,
+            ..controller.clone()
+        };
+        &boostless_controller
+    } else {
+        controller
+    };
+
     let mut next_player = match find_prediction_category(&current) {
         PredictionCategory::Ground => next_player_state_grounded(&current, &controller, time_step)?,
-        //PredictionCategory::Ground2 => next_velocity_grounded2(&current, &controller, time_step),
-        //PredictionCategory::Wall => next_velocity_walled(&current, &controller, time_step),
-        //PredictionCategory::Ceiling => next_velocity_ceilinged(&current, &controller, time_step),
-        //PredictionCategory::CurveWall => next_velocity_curve_walled(&current, &controller, time_step),
-        //PredictionCategory::Air => next_velocity_flying(&current, &controller, time_step),
+        // XXX we can't predict flying yet. the ground prediction is what we've always used here,
+        // and for small bounces it's not far off. but we haven't actually landed, so the jumps
+        // aren't reset
+        PredictionCategory::Air => {
+            let mut next = next_player_state_grounded(&current, &controller, time_step)?;
+            next.on_ground = current.on_ground;
+            next.jumped = current.jumped;
+            next.double_jumped = current.double_jumped;
+            next
+        } //PredictionCategory::Ground2 => next_velocity_grounded2(&current, &controller, time_step),
+          //PredictionCategory::Wall => next_velocity_walled(&current, &controller, time_step),
+          //PredictionCategory::Ceiling => next_velocity_ceilinged(&current, &controller, time_step),
+          //PredictionCategory::CurveWall => next_velocity_curve_walled(&current, &controller, time_step),
     };
 
-    if next_player.position.z < CAR_DIMENSIONS.z / 2.0 {
-        next_player.position.z = CAR_DIMENSIONS.z / 2.0;
+    let min_z = current.hitbox.dimensions().z / 2.0;
+    if next_player.position.z < min_z {
+        next_player.position.z = min_z;
+    }
+
+    next_player.supersonic = next_player.velocity.norm() >= SUPERSONIC_SPEED;
+
+    // NOTE we don't model picking up boost pads here
+    if controller.boost {
+        next_player.boost = (current.boost - BOOST_DEPLETION_RATE * time_step).max(0.0);
     }
 
     Ok(next_player)
@@ -404,35 +561,33 @@ pub fn get_collision(
     time_step: f32,
 ) -> Option<(PlayerState, BallState, Vector3<f32>, f32)> {
     let num_ticks: usize = (time_step / TICK).round() as usize;
-    assert!(num_ticks % 2 == 0);
 
-    // 2-tick steps
+    // 2-tick steps, with a single tick at the end if there's an odd number of them
     let mut last = player.clone();
-    for step in 1..=(num_ticks / 2) {
-        if let Ok(next) = next_player_state(&last, controller, TICK * 2.0) {
-            if let Some(ball) = ball_trajectory.get(step * 2) {
-                if ball_collides(ball, &next) {
-                    // check if one tick earlier collides, since we are using 2-tick steps
-                    if let Ok(next_single_tick) = next_player_state(&last, controller, TICK) {
-                        let single_tick_ball = &ball_trajectory[step * 2 - 1];
-                        if ball_collides(single_tick_ball, &next_single_tick) {
-                            let collision_time = (2 * step - 1) as f32 * TICK;
-                            let point = closest_point_for_collision(single_tick_ball, &next_single_tick);
-                            return Some((next_single_tick, single_tick_ball.clone(), point, collision_time));
-                        }
+    let mut tick = 0;
+    while tick < num_ticks {
+        let step_ticks = (num_ticks - tick).min(2);
+        let next = next_player_state(&last, controller, step_ticks as f32 * TICK).ok()?;
+        let ball = ball_trajectory.get(tick + step_ticks)?;
+        if ball_collides(ball, &next) {
+            // check if one tick earlier collides, since we are using 2-tick steps
+            if step_ticks == 2 {
+                if let Ok(next_single_tick) = next_player_state(&last, controller, TICK) {
+                    let single_tick_ball = &ball_trajectory[tick + 1];
+                    if ball_collides(single_tick_ball, &next_single_tick) {
+                        let collision_time = (tick + 1) as f32 * TICK;
+                        let point = closest_point_for_collision(single_tick_ball, &next_single_tick);
+                        return Some((next_single_tick, single_tick_ball.clone(), point, collision_time));
                     }
-
-                    let collision_time = (2 * step) as f32 * TICK;
-                    let point = closest_point_for_collision(ball, &next);
-                    return Some((next, ball.clone(), point, collision_time));
                 }
-                last = next;
-            } else {
-                return None;
             }
-        } else {
-            return None;
+
+            let collision_time = (tick + step_ticks) as f32 * TICK;
+            let point = closest_point_for_collision(ball, &next);
+            return Some((next, ball.clone(), point, collision_time));
         }
+        last = next;
+        tick += step_ticks;
     }
 
     None
@@ -459,3 +614,50 @@ impl PredictPlayer for PlayerState {
         player
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_prediction(x: f32, yaw: f32) -> PlayerPrediction {
+        (
+            Vector3::new(x, 100.0, 0.0),
+            Vector3::new(x, 1000.0, 0.0),
+            Vector3::new(0.0, 0.0, yaw),
+            Rotation3::from_euler_angles(0.0, 0.0, yaw),
+        )
+    }
+
+    #[test]
+    fn steps_are_composed_from_the_largest_tables() {
+        let steps = |mut remaining: usize| {
+            let mut steps = vec![];
+            while remaining > 0 {
+                let ticks = largest_step_ticks(remaining);
+                steps.push(ticks);
+                remaining -= ticks;
+            }
+            steps
+        };
+        assert_eq!(steps(24), vec![16, 8]);
+        assert_eq!(steps(3), vec![2, 1]);
+        assert_eq!(steps(32), vec![32]);
+        assert_eq!(steps(1), vec![1]);
+    }
+
+    #[test]
+    fn analog_steer_is_in_between() {
+        let (steer1, steer2, factor) = steer_bounds(Steer::Analog(0.25));
+        assert_eq!((steer1, steer2, factor), (Steer::Straight, Steer::Right, 0.25));
+        let (steer1, steer2, factor) = steer_bounds(Steer::Analog(-0.5));
+        assert_eq!((steer1, steer2, factor), (Steer::Straight, Steer::Left, 0.5));
+
+        let straight = test_prediction(0.0, 0.0);
+        let right = test_prediction(-40.0, -0.4);
+        let (translation, velocity, angular_velocity, rotation) = interpolate_prediction(straight, right, 0.25);
+        assert!((translation.x + 10.0).abs() < 0.001);
+        assert!((velocity.x + 10.0).abs() < 0.001);
+        assert!((angular_velocity.z + 0.1).abs() < 0.001);
+        assert!((rotation.euler_angles().2 + 0.1).abs() < 0.001);
+    }
+}
@@ -14,7 +14,11 @@ type MyHasher = BuildHasherDefault<FnvHasher>;
 
 /// the FPS at which samples were recorded
 pub const RECORD_FPS: usize = 120;
-pub const MIN_SAMPLE_LENGTH: usize = 1 + 16; // we want to simulate 16-tick steps. we want 16 ticks after the first measurement
+// we want to simulate 16-tick steps. we want 16 ticks after the first measurement. the 32-tick
+// table only indexes the samples long enough for it, see build_driving_model
+pub const MIN_SAMPLE_LENGTH: usize = 1 + 16;
+// we record long enough for the largest table, 32 ticks after the first measurement
+pub const RECORD_LENGTH: usize = 1 + 32;
 
 lazy_static! {
     pub static ref THROTTLE_STRAIGHT_ALL: Vec<Vec<PlayerState>> =
@@ -147,7 +151,11 @@ pub fn load_sample_file(path: &PathBuf) -> Vec<PlayerState> {
                         .expect("Can't convert yaw to f32"),
                 ),
 
-                team: Team::Blue, // doesn't matter
+                team: Team::Blue,       // doesn't matter
+                boost: MAX_BOOST,       // samples are recorded with unlimited boost
+                hitbox: Hitbox::Octane, // samples are recorded with the fennec
+                // samples are all driving on the ground, which is what the default flags are
+                ..PlayerState::default()
             }
         })
         .collect();
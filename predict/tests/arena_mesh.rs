@@ -0,0 +1,19 @@
+extern crate predict;
+extern crate state;
+
+use predict::arena;
+use state::*;
+
+#[test]
+fn missing_mesh_is_an_error() {
+    arena::set_arena_mesh(ArenaKind::Hoops, "./does/not/exist.obj");
+    assert!(arena::arena(ArenaKind::Hoops).is_err());
+}
+
+#[test]
+fn custom_mesh_is_used() {
+    let mesh_path = format!("{}/../assets/arena.obj", env!("CARGO_MANIFEST_DIR"));
+    arena::set_arena_mesh(ArenaKind::Dropshot, &mesh_path);
+    let dropshot = arena::arena(ArenaKind::Dropshot).expect("Custom mesh should load");
+    assert_eq!(dropshot.kind, ArenaKind::Dropshot);
+}
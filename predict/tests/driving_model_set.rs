@@ -0,0 +1,142 @@
+extern crate bincode;
+extern crate flate2;
+extern crate predict;
+#[macro_use]
+extern crate serde_derive;
+extern crate state;
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use predict::driving_model::{self, DrivingModel, DrivingModelSet, PlayerTransformation, TransformationMap};
+use predict::player;
+use predict::sample::NormalizedPlayerState;
+use predict::PredictError;
+use state::*;
+use std::fs::File;
+use std::fs::{create_dir_all, remove_dir_all};
+
+#[test]
+fn reports_missing_variants() {
+    let dir = std::env::temp_dir().join(format!("brick-driving-models-{}", std::process::id()));
+    create_dir_all(&dir).expect("failed to create model dir");
+
+    match DrivingModelSet::load(&dir) {
+        Err(PredictError::ModelLoad { .. }) => {}
+        _ => panic!("expected an empty model dir to fail loading"),
+    }
+
+    DrivingModel::default()
+        .save(&driving_model::model_path(&dir, "throttle_straight"))
+        .expect("failed to save model");
+    let models = DrivingModelSet::load(&dir).expect("failed to load models");
+    remove_dir_all(&dir).expect("failed to remove model dir");
+
+    assert!(models.get("throttle_straight").is_some());
+    assert!(!models.missing().contains(&"throttle_straight"));
+    assert!(models.missing().contains(&"boost_straight"));
+    assert_eq!(models.missing().len(), driving_model::CONTROLLER_VARIANTS.len() - 1);
+}
+
+#[test]
+fn throttles_without_models_are_rejected() {
+    let dir = std::env::temp_dir().join(format!("brick-forward-models-{}", std::process::id()));
+    create_dir_all(&dir).expect("failed to create model dir");
+    DrivingModel::default()
+        .save(&driving_model::model_path(&dir, "throttle_straight"))
+        .expect("failed to save model");
+    driving_model::set_body_model_dir(Hitbox::Merc, &dir);
+    driving_model::models_for(Hitbox::Merc).expect("failed to load models");
+    remove_dir_all(&dir).expect("failed to remove model dir");
+
+    let player = PlayerState {
+        hitbox: Hitbox::Merc,
+        ..PlayerState::default()
+    };
+    for &(throttle, variant) in &[
+        (Throttle::Reverse, "reverse_straight"),
+        (Throttle::Idle, "idle_straight"),
+        // in between idle and reverse, so it needs both
+        (Throttle::from_value(-0.6), "idle_straight"),
+    ] {
+        let controller = BrickControllerState {
+            throttle,
+            ..BrickControllerState::default()
+        };
+        match player::next_player_state(&player, &controller, 16.0 * TICK) {
+            Err(PredictError::MissingModel(missing)) => assert_eq!(missing, variant),
+            result => panic!("expected a missing {} model, got {:?}", variant, result.map(|_| ())),
+        }
+    }
+}
+
+/// the model file format from before the 32, 8 and 4-tick tables
+#[derive(Serialize)]
+struct LegacyDrivingModel {
+    tick16: TransformationMap,
+    tick2: TransformationMap,
+}
+
+fn standing_still() -> (NormalizedPlayerState, PlayerTransformation) {
+    let normalized = NormalizedPlayerState {
+        local_vx: 0,
+        local_vy: 0,
+        avz: 0,
+    };
+    let transformation = PlayerTransformation {
+        start_local_vx: 0,
+        start_local_vy: 0,
+        translation_x: 0,
+        translation_y: 0,
+        end_velocity_x: 0,
+        end_velocity_y: 0,
+        end_yaw: 0.0,
+        end_angular_velocity_z: 0.0,
+    };
+    (normalized, transformation)
+}
+
+#[test]
+fn loads_models_from_before_the_extra_tables() {
+    let dir = std::env::temp_dir().join(format!("brick-legacy-models-{}", std::process::id()));
+    create_dir_all(&dir).expect("failed to create model dir");
+    let path = driving_model::model_path(&dir, "throttle_straight");
+
+    let (normalized, transformation) = standing_still();
+    let mut legacy = LegacyDrivingModel {
+        tick16: TransformationMap::default(),
+        tick2: TransformationMap::default(),
+    };
+    legacy.tick16.insert(normalized, transformation);
+    let mut encoder = GzEncoder::new(File::create(&path).expect("failed to create model"), Compression::default());
+    bincode::serialize_into(&mut encoder, &legacy).expect("failed to save legacy model");
+    encoder.finish().expect("failed to save legacy model");
+
+    let model = DrivingModel::load(&path.to_string_lossy());
+    remove_dir_all(&dir).expect("failed to remove model dir");
+    let model = model.expect("failed to load legacy model");
+    assert!(model.tick16.contains_key(&normalized));
+    assert!(model.tick32.is_empty());
+}
+
+#[test]
+fn missing_transformations_fall_back_to_smaller_steps() {
+    let dir = std::env::temp_dir().join(format!("brick-sparse-models-{}", std::process::id()));
+    create_dir_all(&dir).expect("failed to create model dir");
+    let (normalized, transformation) = standing_still();
+    let mut model = DrivingModel::default();
+    model.tick16.insert(normalized, transformation);
+    model
+        .save(&driving_model::model_path(&dir, "throttle_straight"))
+        .expect("failed to save model");
+    driving_model::set_body_model_dir(Hitbox::Hybrid, &dir);
+    driving_model::models_for(Hitbox::Hybrid).expect("failed to load models");
+    remove_dir_all(&dir).expect("failed to remove model dir");
+
+    let player = PlayerState {
+        hitbox: Hitbox::Hybrid,
+        ..PlayerState::default()
+    };
+    // nothing in the 32-tick table, so this is two 16-tick steps
+    let controller = BrickControllerState::default();
+    assert!(player::next_player_state(&player, &controller, 32.0 * TICK).is_ok());
+}
@@ -0,0 +1,82 @@
+extern crate nalgebra as na;
+extern crate predict;
+extern crate state;
+
+use na::Vector3;
+use predict::arena::Arena;
+use predict::ball;
+use state::*;
+
+fn soccar() -> Arena {
+    let mesh_path = format!("{}/../assets/arena.obj", env!("CARGO_MANIFEST_DIR"));
+    Arena::load(ArenaKind::Soccar, &mesh_path).expect("Couldn't load soccar arena")
+}
+
+fn shot(position: Vector3<f32>, velocity: Vector3<f32>) -> BallState {
+    BallState {
+        position,
+        velocity,
+        ..BallState::default()
+    }
+}
+
+#[test]
+fn straight_shot_goes_in() {
+    let arena = soccar();
+    let ball = shot(
+        Vector3::new(0.0, BACK_WALL_DISTANCE - 1500.0, BALL_COLLISION_RADIUS),
+        Vector3::new(0.0, 2000.0, 0.0),
+    );
+    let entry_time = ball::trajectory_enters_goal(&ball, 3.0, &arena).expect("Expected a goal");
+
+    // the first tick the whole ball is over the line
+    let goal = arena.goal(Team::Orange).unwrap();
+    let entry_index = ball::ball_trajectory(&ball, 3.0, &arena)
+        .iter()
+        .position(|ball| goal.is_scored(&ball.position, arena.ball_collision_radius))
+        .unwrap();
+    assert_eq!(entry_time, entry_index as f32 * TICK);
+    assert!(entry_time > 0.7 && entry_time < 1.0, "entry time: {}", entry_time);
+}
+
+#[test]
+fn shot_off_the_post_is_rejected() {
+    let arena = soccar();
+    let ball = shot(
+        Vector3::new(GOAL_X, BACK_WALL_DISTANCE - 1500.0, BALL_COLLISION_RADIUS),
+        Vector3::new(0.0, 2000.0, 0.0),
+    );
+    assert_eq!(ball::trajectory_enters_goal(&ball, 3.0, &arena), None);
+}
+
+#[test]
+fn shot_off_the_crossbar_is_rejected() {
+    let arena = soccar();
+    let ball = shot(
+        Vector3::new(0.0, BACK_WALL_DISTANCE - 1500.0, GOAL_Z + 170.0),
+        Vector3::new(0.0, 2000.0, 0.0),
+    );
+    assert_eq!(ball::trajectory_enters_goal(&ball, 3.0, &arena), None);
+}
+
+#[test]
+fn bank_shot_goes_in() {
+    let arena = soccar();
+    // heading for the side wall, away from the goal, so it only goes in off the wall
+    let angle = 22.0f32.to_radians();
+    let ball = shot(
+        Vector3::new(2500.0, BACK_WALL_DISTANCE - 2500.0, 300.0),
+        Vector3::new(5000.0 * angle.cos(), 5000.0 * angle.sin(), 300.0),
+    );
+    assert!(ball::trajectory_enters_goal(&ball, 3.0, &arena).is_some());
+}
+
+#[test]
+fn ball_heading_away_is_rejected() {
+    let arena = soccar();
+    let ball = shot(
+        Vector3::new(0.0, BACK_WALL_DISTANCE - 1500.0, BALL_COLLISION_RADIUS),
+        Vector3::new(0.0, -2000.0, 0.0),
+    );
+    assert_eq!(ball::trajectory_enters_goal(&ball, 3.0, &arena), None);
+}
@@ -0,0 +1,17 @@
+extern crate predict;
+extern crate state;
+
+use predict::PredictError;
+use state::*;
+
+#[test]
+fn partial_ticks_are_an_error() {
+    let player = PlayerState::default();
+    let controller = BrickControllerState::default();
+    for &time_step in &[1.5 * TICK, 0.0] {
+        match predict::player::next_player_state(&player, &controller, time_step) {
+            Err(PredictError::UnsupportedTimeStep(_)) => {}
+            _ => panic!("expected an unsupported time step error for {}", time_step),
+        }
+    }
+}
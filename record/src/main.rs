@@ -1,5 +1,6 @@
 extern crate csv;
 extern crate flatbuffers;
+extern crate nalgebra as na;
 extern crate predict;
 extern crate rand;
 extern crate rlbot;
@@ -9,6 +10,7 @@ extern crate state;
 #[macro_use]
 extern crate static_assertions;
 
+use na::Vector3;
 use rand::{thread_rng, Rng};
 use rlbot::ControllerState;
 use spin_sleep::LoopHelper;
@@ -16,7 +18,6 @@ use state::*;
 use std::cell::RefCell;
 use std::collections::HashMap;
 use std::error::Error;
-use std::f32::consts::PI;
 use std::fs::create_dir_all;
 
 const MAX_BOOST_SPEED: i16 = 2300;
@@ -142,27 +143,16 @@ impl RecordState {
     pub fn set_next_game_state(&mut self, rlbot: &rlbot::RLBot) -> Result<(), Box<dyn Error>> {
         self.started = false;
 
-        let position = rlbot::Vector3Partial::new().x(0.0).y(0.0).z(RESTING_Z);
+        // default rotation points towards +y
+        let player = PlayerState {
+            position: Vector3::new(0.0, 0.0, Hitbox::default().resting_z()),
+            velocity: Vector3::new(self.local_vx as f32, self.local_vy as f32, 0.0),
+            angular_velocity: Vector3::new(0.0, 0.0, self.angular_speed as f32 * ANGULAR_GRID),
+            ..PlayerState::default()
+        };
 
-        let velocity = rlbot::Vector3Partial::new()
-            .x(-self.local_vx as f32)
-            .y(self.local_vy as f32)
-            .z(0.0);
-
-        let angular_velocity = rlbot::Vector3Partial::new()
-            .x(0.0)
-            .y(0.0)
-            .z(self.angular_speed as f32 * ANGULAR_GRID);
-
-        let rotation = rlbot::RotatorPartial::new().pitch(0.0).yaw(PI / 2.0).roll(0.0);
-
-        let physics = rlbot::DesiredPhysics::new()
-            .location(position)
-            .rotation(rotation)
-            .velocity(velocity)
-            .angular_velocity(angular_velocity);
-
-        let car_state = rlbot::DesiredCarState::new().physics(physics);
+        // only the physics, leaving boost and jumps to the game
+        let car_state = rlbot::DesiredCarState::new().physics(rlbot::DesiredPhysics::from(&player));
 
         let desired_game_state = rlbot::DesiredGameState::new().car_state(0, car_state);
 
@@ -172,21 +162,13 @@ impl RecordState {
     }
 
     pub fn reset_game_state(&mut self, rlbot: &rlbot::RLBot) -> Result<(), Box<dyn Error>> {
-        let position = rlbot::Vector3Partial::new().x(-2000.0).y(2000.0).z(RESTING_Z);
-
-        let velocity = rlbot::Vector3Partial::new().x(0.0).y(0.0).z(0.0);
-
-        let angular_velocity = rlbot::Vector3Partial::new().x(0.0).y(0.0).z(0.0);
-
-        let rotation = rlbot::RotatorPartial::new().pitch(0.0).yaw(PI / 2.0).roll(0.0);
-
-        let physics = rlbot::DesiredPhysics::new()
-            .location(position)
-            .rotation(rotation)
-            .velocity(velocity)
-            .angular_velocity(angular_velocity);
+        let player = PlayerState {
+            position: Vector3::new(2000.0, 2000.0, Hitbox::default().resting_z()),
+            ..PlayerState::default()
+        };
 
-        let car_state = rlbot::DesiredCarState::new().physics(physics);
+        // only the physics, leaving boost and jumps to the game
+        let car_state = rlbot::DesiredCarState::new().physics(rlbot::DesiredPhysics::from(&player));
 
         let desired_game_state = rlbot::DesiredGameState::new().car_state(0, car_state);
 
@@ -333,7 +315,7 @@ impl RecordState {
     }
 
     pub fn sample_complete(&self) -> bool {
-        self.records.len() >= predict::sample::MIN_SAMPLE_LENGTH
+        self.records.len() >= predict::sample::RECORD_LENGTH
     }
 
     pub fn sample_valid(&self) -> bool {
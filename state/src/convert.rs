@@ -0,0 +1,321 @@
+//! conversion to and from rlbot's coordinate system. rlbot gives us the game's left handed
+//! coordinates, but we flip the x axis so that x is positive towards the right, since that's the
+//! only thing that makes sense. rotations have to be converted to match.
+//!
+//! everything crossing the rlbot boundary, ie reading tick packets and setting desired state,
+//! should go through here so the two directions can't drift apart.
+
+use na::{Quaternion, UnitQuaternion, Vector3};
+
+use crate::*;
+
+pub fn vector_from_rlbot(v: &rlbot::Vector3) -> Vector3<f32> {
+    Vector3::new(-v.x, v.y, v.z)
+}
+
+pub fn vector_to_rlbot(v: &Vector3<f32>) -> rlbot::Vector3 {
+    rlbot::Vector3 { x: -v.x, y: v.y, z: v.z }
+}
+
+fn vector_partial_to_rlbot(v: &Vector3<f32>) -> rlbot::Vector3Partial {
+    let v = vector_to_rlbot(v);
+    rlbot::Vector3Partial::new().x(v.x).y(v.y).z(v.z)
+}
+
+/// converts between right handed and left handed quaternions (goes with the x axis flip). this is
+/// its own inverse, so it works in both directions.
+/// https://stackoverflow.com/a/34366144/127219
+fn flip_handedness(uq: &UnitQuaternion<f32>) -> UnitQuaternion<f32> {
+    let q = uq.quaternion();
+    UnitQuaternion::from_quaternion(Quaternion::new(q.scalar(), -q.vector()[0], q.vector()[1], -q.vector()[2]))
+}
+
+pub fn rotation_from_rlbot(r: &rlbot::Rotator) -> UnitQuaternion<f32> {
+    flip_handedness(&UnitQuaternion::from_euler_angles(r.roll, r.pitch, r.yaw))
+}
+
+pub fn rotation_to_rlbot(rotation: &UnitQuaternion<f32>) -> rlbot::Rotator {
+    let (roll, pitch, yaw) = flip_handedness(rotation).euler_angles();
+    rlbot::Rotator { pitch, yaw, roll }
+}
+
+/// fills in only the given components, keeping the rest of the vector as is
+fn apply_vector_partial(v: &mut Vector3<f32>, partial: &Option<rlbot::Vector3Partial>) {
+    if let Some(partial) = partial {
+        let mut rlbot_v = vector_to_rlbot(v);
+        rlbot_v.x = partial.x.unwrap_or(rlbot_v.x);
+        rlbot_v.y = partial.y.unwrap_or(rlbot_v.y);
+        rlbot_v.z = partial.z.unwrap_or(rlbot_v.z);
+        *v = vector_from_rlbot(&rlbot_v);
+    }
+}
+
+fn apply_rotator_partial(rotation: &mut UnitQuaternion<f32>, partial: &Option<rlbot::RotatorPartial>) {
+    if let Some(partial) = partial {
+        let mut rotator = rotation_to_rlbot(rotation);
+        rotator.pitch = partial.pitch.unwrap_or(rotator.pitch);
+        rotator.yaw = partial.yaw.unwrap_or(rotator.yaw);
+        rotator.roll = partial.roll.unwrap_or(rotator.roll);
+        *rotation = rotation_from_rlbot(&rotator);
+    }
+}
+
+fn team_from_rlbot(team: i32) -> Team {
+    match team {
+        0 => Team::Blue,
+        1 => Team::Orange,
+        _ => unimplemented!(),
+    }
+}
+
+impl PlayerState {
+    pub fn set_physics(&mut self, physics: &rlbot::Physics) {
+        self.position = vector_from_rlbot(&physics.location);
+        self.velocity = vector_from_rlbot(&physics.velocity);
+        self.angular_velocity = vector_from_rlbot(&physics.angular_velocity);
+        self.rotation = rotation_from_rlbot(&physics.rotation);
+    }
+
+    /// the inverse of From<&PlayerState> for rlbot::DesiredCarState. only the parts of the
+    /// desired state that are set get applied
+    pub fn apply_desired_state(&mut self, desired: &rlbot::DesiredCarState) {
+        if let Some(physics) = &desired.physics {
+            apply_vector_partial(&mut self.position, &physics.location);
+            apply_vector_partial(&mut self.velocity, &physics.velocity);
+            apply_vector_partial(&mut self.angular_velocity, &physics.angular_velocity);
+            apply_rotator_partial(&mut self.rotation, &physics.rotation);
+        }
+
+        if let Some(boost) = desired.boost_amount {
+            self.boost = boost;
+        }
+
+        if let Some(jumped) = desired.jumped {
+            self.jumped = jumped;
+        }
+
+        if let Some(double_jumped) = desired.double_jumped {
+            self.double_jumped = double_jumped;
+        }
+    }
+}
+
+impl BallState {
+    pub fn set_physics(&mut self, physics: &rlbot::Physics) {
+        self.position = vector_from_rlbot(&physics.location);
+        self.velocity = vector_from_rlbot(&physics.velocity);
+        self.angular_velocity = vector_from_rlbot(&physics.angular_velocity);
+    }
+
+    /// the inverse of From<&BallState> for rlbot::DesiredBallState. only the parts of the desired
+    /// state that are set get applied
+    pub fn apply_desired_state(&mut self, desired: &rlbot::DesiredBallState) {
+        if let Some(physics) = &desired.physics {
+            apply_vector_partial(&mut self.position, &physics.location);
+            apply_vector_partial(&mut self.velocity, &physics.velocity);
+            apply_vector_partial(&mut self.angular_velocity, &physics.angular_velocity);
+        }
+    }
+}
+
+impl From<&rlbot::PlayerInfo> for PlayerState {
+    fn from(player: &rlbot::PlayerInfo) -> Self {
+        let mut player_state = PlayerState::default();
+        player_state.set_physics(&player.physics);
+
+        player_state.team = team_from_rlbot(player.team);
+
+        player_state.boost = player.boost as f32;
+        player_state.on_ground = player.has_wheel_contact;
+        player_state.jumped = player.jumped;
+        player_state.double_jumped = player.double_jumped;
+        player_state.supersonic = player.is_supersonic;
+        player_state.demolished = player.is_demolished;
+
+        let hitbox = &player.hitbox;
+        player_state.hitbox = Hitbox::closest(&Vector3::new(hitbox.length, hitbox.width, hitbox.height));
+
+        player_state
+    }
+}
+
+impl From<&rlbot::Touch> for BallTouch {
+    fn from(touch: &rlbot::Touch) -> Self {
+        BallTouch {
+            player_index: touch.player_index as usize,
+            team: team_from_rlbot(touch.team),
+            seconds_elapsed: touch.game_seconds,
+        }
+    }
+}
+
+impl From<&rlbot::Physics> for BallState {
+    fn from(physics: &rlbot::Physics) -> Self {
+        let mut ball = BallState::default();
+        ball.set_physics(physics);
+        ball
+    }
+}
+
+impl From<&PlayerState> for rlbot::Physics {
+    fn from(player: &PlayerState) -> Self {
+        rlbot::Physics {
+            location: vector_to_rlbot(&player.position),
+            rotation: rotation_to_rlbot(&player.rotation),
+            velocity: vector_to_rlbot(&player.velocity),
+            angular_velocity: vector_to_rlbot(&player.angular_velocity),
+        }
+    }
+}
+
+impl From<&BallState> for rlbot::Physics {
+    fn from(ball: &BallState) -> Self {
+        rlbot::Physics {
+            location: vector_to_rlbot(&ball.position),
+            // we don't track ball rotation
+            rotation: rlbot::Rotator {
+                pitch: 0.0,
+                yaw: 0.0,
+                roll: 0.0,
+            },
+            velocity: vector_to_rlbot(&ball.velocity),
+            angular_velocity: vector_to_rlbot(&ball.angular_velocity),
+        }
+    }
+}
+
+impl From<&PlayerState> for rlbot::DesiredPhysics {
+    fn from(player: &PlayerState) -> Self {
+        let rotator = rotation_to_rlbot(&player.rotation);
+        let rotation = rlbot::RotatorPartial::new()
+            .pitch(rotator.pitch)
+            .yaw(rotator.yaw)
+            .roll(rotator.roll);
+        rlbot::DesiredPhysics::new()
+            .location(vector_partial_to_rlbot(&player.position))
+            .rotation(rotation)
+            .velocity(vector_partial_to_rlbot(&player.velocity))
+            .angular_velocity(vector_partial_to_rlbot(&player.angular_velocity))
+    }
+}
+
+/// the whole car state, including boost and jumps. use DesiredPhysics to only move the car
+impl From<&PlayerState> for rlbot::DesiredCarState {
+    fn from(player: &PlayerState) -> Self {
+        rlbot::DesiredCarState::new()
+            .physics(rlbot::DesiredPhysics::from(player))
+            .boost_amount(player.boost)
+            .jumped(player.jumped)
+            .double_jumped(player.double_jumped)
+    }
+}
+
+impl From<&BallState> for rlbot::DesiredBallState {
+    fn from(ball: &BallState) -> Self {
+        let physics = rlbot::DesiredPhysics::new()
+            .location(vector_partial_to_rlbot(&ball.position))
+            .velocity(vector_partial_to_rlbot(&ball.velocity))
+            .angular_velocity(vector_partial_to_rlbot(&ball.angular_velocity));
+
+        rlbot::DesiredBallState::new().physics(physics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    fn assert_same_player(a: &PlayerState, b: &PlayerState) {
+        assert!((a.position - b.position).norm() < 0.01);
+        assert!((a.velocity - b.velocity).norm() < 0.01);
+        assert!((a.angular_velocity - b.angular_velocity).norm() < 0.01);
+        assert!(a.rotation.angle_to(&b.rotation) < 0.001);
+        assert!((a.boost - b.boost).abs() < 0.01);
+        assert_eq!(a.jumped, b.jumped);
+        assert_eq!(a.double_jumped, b.double_jumped);
+    }
+
+    fn assert_same_ball(a: &BallState, b: &BallState) {
+        assert!((a.position - b.position).norm() < 0.01);
+        assert!((a.velocity - b.velocity).norm() < 0.01);
+        assert!((a.angular_velocity - b.angular_velocity).norm() < 0.01);
+    }
+
+    fn test_player() -> PlayerState {
+        PlayerState {
+            position: Vector3::new(-1234.0, 2345.0, 300.0),
+            velocity: Vector3::new(500.0, -600.0, 70.0),
+            angular_velocity: Vector3::new(1.0, -2.0, 3.0),
+            rotation: UnitQuaternion::from_euler_angles(0.3, -0.2, 2.5),
+            team: Team::Blue,
+            boost: 42.0,
+            hitbox: Hitbox::Plank,
+            on_ground: false,
+            jumped: true,
+            double_jumped: true,
+            supersonic: false,
+            demolished: false,
+        }
+    }
+
+    fn test_ball() -> BallState {
+        BallState {
+            position: Vector3::new(1000.0, -2000.0, 500.0),
+            velocity: Vector3::new(-300.0, 400.0, -50.0),
+            angular_velocity: Vector3::new(0.5, 1.5, -2.5),
+        }
+    }
+
+    #[test]
+    fn test_rlbot_conversion_flips_x() {
+        let physics = rlbot::Physics::from(&test_player());
+        assert!((physics.location.x - 1234.0).abs() < 0.001);
+        assert!((physics.location.y - 2345.0).abs() < 0.001);
+
+        // the default player points towards +y in both coordinate systems, ie a yaw of PI / 2
+        let rotator = rotation_to_rlbot(&PlayerState::default().rotation);
+        assert!((rotator.yaw - PI / 2.0).abs() < 0.001);
+        assert!(rotator.pitch.abs() < 0.001);
+        assert!(rotator.roll.abs() < 0.001);
+    }
+
+    #[test]
+    fn test_rlbot_physics_round_trip() {
+        let player = test_player();
+        let mut round_tripped = PlayerState::default();
+        round_tripped.set_physics(&rlbot::Physics::from(&player));
+        round_tripped.boost = player.boost;
+        round_tripped.jumped = player.jumped;
+        round_tripped.double_jumped = player.double_jumped;
+        assert_same_player(&player, &round_tripped);
+
+        let ball = test_ball();
+        assert_same_ball(&ball, &BallState::from(&rlbot::Physics::from(&ball)));
+    }
+
+    #[test]
+    fn test_rlbot_desired_state_round_trip() {
+        let player = test_player();
+        let mut round_tripped = PlayerState::default();
+        round_tripped.apply_desired_state(&rlbot::DesiredCarState::from(&player));
+        assert_same_player(&player, &round_tripped);
+
+        let ball = test_ball();
+        let mut round_tripped = BallState::default();
+        round_tripped.apply_desired_state(&rlbot::DesiredBallState::from(&ball));
+        assert_same_ball(&ball, &round_tripped);
+    }
+
+    #[test]
+    fn test_rlbot_partial_desired_state() {
+        let player = test_player();
+        let position = rlbot::Vector3Partial::new().z(17.0);
+        let desired = rlbot::DesiredCarState::new().physics(rlbot::DesiredPhysics::new().location(position));
+
+        let mut updated = player.clone();
+        updated.apply_desired_state(&desired);
+        assert!((updated.position - Vector3::new(-1234.0, 2345.0, 17.0)).norm() < 0.001);
+        assert!(updated.rotation.angle_to(&player.rotation) < 0.001);
+    }
+}
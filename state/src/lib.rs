@@ -7,17 +7,28 @@ extern crate lazy_static;
 #[macro_use]
 extern crate serde_derive;
 extern crate bincode;
+extern crate serde_json;
 
-use na::{Point3, Quaternion, Rotation3, UnitQuaternion, Vector3};
+use na::{Point3, Rotation3, UnitQuaternion, Vector3};
 use std::collections::VecDeque;
 use std::f32::consts::PI;
+use std::time::{Duration, Instant};
+
+mod convert;
+pub use convert::{rotation_from_rlbot, rotation_to_rlbot, vector_from_rlbot, vector_to_rlbot};
+
+mod mirror;
+pub use mirror::Mirror;
+
+mod snapshot;
+pub use snapshot::{Snapshot, SNAPSHOT_VERSION};
 
 // general constants
 pub const FPS: f32 = 120.0;
 pub const TICK: f32 = 1.0 / FPS; // matches RL's internal fixed physics tick rate
 pub const LAG_FRAMES: usize = 0; // turns out there isn't actually much lag
 
-// arena constants
+// soccar arena constants. see predict::arena::Arena for the other arenas
 pub const SIDE_WALL_DISTANCE: f32 = 4096.0;
 pub const SIDE_CURVE_DISTANCE: f32 = 3838.0;
 pub const BACK_WALL_DISTANCE: f32 = 5140.0;
@@ -26,17 +37,27 @@ pub const CEILING_DISTANCE: f32 = 2044.0;
 pub const GOAL_X: f32 = 892.75;
 pub const GOAL_Z: f32 = 640.0;
 
+/// how far the ball can be from the centre spot, horizontally, and still count as a kickoff
+pub const KICKOFF_BALL_TOLERANCE: f32 = 10.0;
+
 // car constants
 pub const MAX_BOOST_SPEED: f32 = 2300.0; // TODO rename to MAX_SPEED
+pub const SUPERSONIC_SPEED: f32 = 2200.0;
 pub const MAX_ANGULAR_SPEED: f32 = 5.5;
 pub const MAX_GROUND_ANGULAR_SPEED: f32 = 4.4; // NOTE this is based on the turning sample collection, though we might be able to redo a few samples to move this up
 
-//pub const RESTING_Z: f32 = 18.65; // batmobile
-pub const RESTING_Z: f32 = 17.01; // fennec
 pub const RESTING_Z_VELOCITY: f32 = 8.0; // TODO double check
 
+// boost constants
+pub const MAX_BOOST: f32 = 100.0;
+pub const BOOST_DEPLETION_RATE: f32 = 100.0 / 3.0; // boost per second
+pub const FULL_BOOST_PAD_AMOUNT: f32 = 100.0;
+pub const SMALL_BOOST_PAD_AMOUNT: f32 = 12.0;
+pub const FULL_BOOST_PAD_RESPAWN_TIME: f32 = 10.0;
+pub const SMALL_BOOST_PAD_RESPAWN_TIME: f32 = 4.0;
+
 // source: https://github.com/samuelpmish/RLUtilities/blob/master/src/simulation/ball.cc#L17
-// TODO handle hoops/dropshot radii
+// NOTE these are for soccar, see predict::arena::Arena for the other arenas
 pub const BALL_INERTIAL_RADIUS: f32 = 91.25;
 pub const BALL_COLLISION_RADIUS: f32 = 93.15;
 
@@ -46,26 +67,24 @@ pub const CAR_MASS: f32 = 180.0;
 // https://github.com/rustsim/nalgebra/issues/521
 lazy_static! {
     // source: https://github.com/samuelpmish/RLUtilities/blob/master/src/simulation/car.cc#L369
-    pub static ref CAR_INERTIA: na::Matrix3<f32> = CAR_MASS * na::Matrix3::new(
+    // NOTE this is for the octane, we don't know the inertia for the other car bodies. see Hitbox
+    static ref CAR_INERTIA: na::Matrix3<f32> = CAR_MASS * na::Matrix3::new(
         751.0,    0.0,    0.0,
         0.0  , 1334.0,    0.0,
         0.0  ,    0.0, 1836.0,
     );
-    pub static ref CAR_INVERSE_INERTIA: na::Matrix3<f32> = CAR_INERTIA.try_inverse().expect("Inverse car inertia failed");
-
-    // batmobile
-    //pub static ref CAR_DIMENSIONS: Vector3<f32> = Vector3::new(128.8198, 84.67036, 29.3944);
-    //pub static ref CAR_OFFSET: Vector3<f32> = Vector3::new(-9.008572, 0.0, 12.0942);
-    // TODO switch to octane after building new driving model
-    // octane/fennec
-    pub static ref CAR_DIMENSIONS: Vector3<f32> = Vector3::new(118.0074, 84.19941, 36.15907);
-    pub static ref CAR_OFFSET: Vector3<f32> = Vector3::new(-13.87566, 0.0, 20.75499);
+    static ref CAR_INVERSE_INERTIA: na::Matrix3<f32> = CAR_INERTIA.try_inverse().expect("Inverse car inertia failed");
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 pub enum Action {
     Shoot,
-    //Shadow,
+    /// stay between the ball and our goal
+    Shadow,
+    /// stop a ball that's going into our goal
+    Save,
+    /// the kickoff routine for whichever spawn we're at
+    Kickoff,
     //GoToMid, // XXX not a real action, just a test
 }
 
@@ -99,27 +118,10 @@ impl From<&rlbot::ControllerState> for FullController {
 }
 
 impl From<&rlbot::ControllerState> for BrickControllerState {
-    /// NOTE this is approximate
     fn from(ctrl: &rlbot::ControllerState) -> Self {
-        let throttle = if ctrl.throttle.abs() < 0.2 {
-            Throttle::Idle
-        } else if ctrl.throttle >= 0.0 {
-            Throttle::Forward
-        } else {
-            Throttle::Reverse
-        };
-
-        let steer = if ctrl.steer.abs() < 0.25 {
-            Steer::Straight
-        } else if ctrl.steer >= 0.0 {
-            Steer::Right
-        } else {
-            Steer::Left
-        };
-
         BrickControllerState {
-            throttle,
-            steer,
+            throttle: Throttle::from_value(ctrl.throttle),
+            steer: Steer::from_value(ctrl.steer),
             pitch: ctrl.pitch,
             yaw: ctrl.yaw,
             roll: ctrl.roll,
@@ -133,16 +135,8 @@ impl From<&rlbot::ControllerState> for BrickControllerState {
 impl From<&BrickControllerState> for rlbot::ControllerState {
     fn from(controller: &BrickControllerState) -> Self {
         rlbot::ControllerState {
-            throttle: match controller.throttle {
-                Throttle::Idle => 0.0,
-                Throttle::Forward => 1.0,
-                Throttle::Reverse => -1.0,
-            },
-            steer: match controller.steer {
-                Steer::Straight => 0.0,
-                Steer::Left => -1.0,
-                Steer::Right => 1.0,
-            },
+            throttle: controller.throttle.value(),
+            steer: controller.steer.value(),
             pitch: controller.pitch,
             yaw: controller.yaw,
             roll: controller.roll,
@@ -162,16 +156,136 @@ pub struct BotState {
     pub cost_diff: f32,
     pub controller_history: VecDeque<BrickControllerState>,
     pub turn_errors: VecDeque<f32>,
-    pub last_action: Option<Action>,
+    pub strategy: Option<Strategy>,
+}
+
+/// the action we're committed to, and what things looked like when we picked it. see
+/// brain::strategy for how it's picked and expired
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Strategy {
+    pub action: Action,
+    /// how good the action looked when we picked it. we give up on the action once it looks a lot
+    /// worse than this
+    pub entry_score: f32,
+    pub started_frame: u32,
+    /// the latest touch when we picked the action, so we can tell if the ball was touched since
+    pub last_touch: Option<BallTouch>,
+    /// planning attempts that failed in a row while on this action
+    pub failed_plans: u32,
+}
+
+/// who last touched the ball, and when
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BallTouch {
+    pub player_index: usize,
+    pub team: Team,
+    /// game time of the touch, same clock as MatchInfo::seconds_elapsed
+    pub seconds_elapsed: f32,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct GameState {
     pub ball: BallState,
+    /// our own player. starts out as a copy of players[player_index], but may be further adjusted
+    /// (eg for lag compensation), so use this when referring to ourselves
     pub player: PlayerState,
+    /// every car in the match, including our own, in the same order as the tick packet
+    pub players: Vec<PlayerState>,
+    pub player_index: usize,
+    pub boost_pads: Vec<BoostPad>,
+    pub arena: ArenaKind,
+    pub match_info: MatchInfo,
+    pub last_touch: Option<BallTouch>,
     pub frame: u32,
 }
 
+impl GameState {
+    /// whether we're in a kickoff: the packet says so, and the ball is still on the centre spot
+    pub fn is_kickoff(&self) -> bool {
+        self.match_info.is_kickoff_pause
+            && self.ball.position.x.abs() < KICKOFF_BALL_TOLERANCE
+            && self.ball.position.y.abs() < KICKOFF_BALL_TOLERANCE
+    }
+
+    /// all other players on our team, along with their index in players
+    pub fn teammates<'a>(&'a self) -> impl Iterator<Item = (usize, &'a PlayerState)> + 'a {
+        let team = self.player.team;
+        let player_index = self.player_index;
+        self.players
+            .iter()
+            .enumerate()
+            .filter(move |(i, p)| *i != player_index && p.team == team)
+    }
+
+    /// all players on the other team, along with their index in players
+    pub fn opponents<'a>(&'a self) -> impl Iterator<Item = (usize, &'a PlayerState)> + 'a {
+        let team = self.player.team;
+        self.players.iter().enumerate().filter(move |(_, p)| p.team != team)
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ArenaKind {
+    #[default]
+    Soccar,
+    Hoops,
+    Dropshot,
+}
+
+impl ArenaKind {
+    /// rlbot doesn't tell us the game mode in the tick packet, but the boost pad layout gives it away
+    pub fn from_field_info(field_info: &rlbot::FieldInfo) -> ArenaKind {
+        ArenaKind::from_boost_pad_count(field_info.boost_pads.len())
+    }
+
+    fn from_boost_pad_count(count: usize) -> ArenaKind {
+        match count {
+            HOOPS_BOOST_PAD_COUNT => ArenaKind::Hoops,
+            0 => ArenaKind::Dropshot,
+            _ => ArenaKind::Soccar,
+        }
+    }
+}
+
+const HOOPS_BOOST_PAD_COUNT: usize = 20;
+
+/// the clock, score and other match-level context from the tick packet
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct MatchInfo {
+    pub seconds_elapsed: f32,
+    /// counts down to zero in regulation time. in overtime, rlbot keeps this at zero
+    pub seconds_remaining: f32,
+    pub is_unlimited_time: bool,
+    pub is_overtime: bool,
+    /// false during goal replays, and also during the countdown before a kickoff
+    pub is_round_active: bool,
+    /// true from the end of the kickoff countdown until the ball is first touched
+    pub is_kickoff_pause: bool,
+    pub is_match_ended: bool,
+    pub blue_score: u32,
+    pub orange_score: u32,
+}
+
+impl MatchInfo {
+    pub fn score(&self, team: Team) -> u32 {
+        match team {
+            Team::Blue => self.blue_score,
+            Team::Orange => self.orange_score,
+        }
+    }
+
+    /// positive when the given team is ahead
+    pub fn goal_difference(&self, team: Team) -> i32 {
+        self.score(team) as i32 - self.score(team.opponent()) as i32
+    }
+
+    /// whether the clock can run out on us, ie we're in the last seconds of regulation time.
+    /// overtime and unlimited time matches never end on the clock
+    pub fn is_ending_soon(&self, seconds: f32) -> bool {
+        !self.is_unlimited_time && !self.is_overtime && self.seconds_remaining <= seconds
+    }
+}
+
 // FIXME check if this order matches up with team integers we get from rlbot interface
 #[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
 pub enum Team {
@@ -179,6 +293,15 @@ pub enum Team {
     Orange,
 }
 
+impl Team {
+    pub fn opponent(self) -> Team {
+        match self {
+            Team::Blue => Team::Orange,
+            Team::Orange => Team::Blue,
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub struct PlayerState {
     pub position: Vector3<f32>,
@@ -186,6 +309,14 @@ pub struct PlayerState {
     pub angular_velocity: Vector3<f32>,
     pub rotation: UnitQuaternion<f32>, // FIXME switch to Rotation3!
     pub team: Team,
+    pub boost: f32,
+    pub hitbox: Hitbox,
+    /// wheels touching any surface, including walls and the ceiling
+    pub on_ground: bool,
+    pub jumped: bool,
+    pub double_jumped: bool,
+    pub supersonic: bool,
+    pub demolished: bool,
     //pub rotation: Rotation3<f32>,
 }
 
@@ -197,13 +328,20 @@ impl Default for PlayerState {
             angular_velocity: Vector3::new(0.0, 0.0, 0.0),
             rotation: UnitQuaternion::from_euler_angles(0.0, 0.0, -PI / 2.0),
             team: Team::Blue,
+            boost: MAX_BOOST,
+            hitbox: Hitbox::default(),
+            on_ground: true,
+            jumped: false,
+            double_jumped: false,
+            supersonic: false,
+            demolished: false,
         }
     }
 }
 
 impl PlayerState {
     pub fn hitbox_center(&self) -> Vector3<f32> {
-        self.position + self.rotation.to_rotation_matrix() * (*CAR_OFFSET)
+        self.position + self.rotation.to_rotation_matrix() * self.hitbox.offset()
     }
 
     pub fn heading(&self) -> Vector3<f32> {
@@ -220,6 +358,180 @@ impl PlayerState {
     }
 }
 
+/// the standard hitbox classes that every car body maps to
+// source: the hitbox table in the RLBot wiki's useful game values page
+#[derive(Serialize, Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Hitbox {
+    /// fennec, which is what we've recorded all our samples with
+    #[default]
+    Octane,
+    Dominus,
+    Plank,
+    Breakout,
+    Hybrid,
+    Merc,
+}
+
+pub const HITBOXES: [Hitbox; 6] = [
+    Hitbox::Octane,
+    Hitbox::Dominus,
+    Hitbox::Plank,
+    Hitbox::Breakout,
+    Hitbox::Hybrid,
+    Hitbox::Merc,
+];
+
+impl Hitbox {
+    /// length, width and height of the hitbox
+    pub fn dimensions(self) -> Vector3<f32> {
+        match self {
+            Hitbox::Octane => Vector3::new(118.0074, 84.19941, 36.15907),
+            Hitbox::Dominus => Vector3::new(127.9268, 83.27995, 31.3),
+            Hitbox::Plank => Vector3::new(128.8198, 84.67036, 29.3944),
+            Hitbox::Breakout => Vector3::new(131.4924, 80.521, 30.3),
+            Hitbox::Hybrid => Vector3::new(127.0192, 82.18787, 34.15907),
+            Hitbox::Merc => Vector3::new(120.7205, 76.71289, 41.66172),
+        }
+    }
+
+    /// hitbox center relative to the car's position, in local coordinates. the car with no rotation
+    /// points towards negative x, so a hitbox shifted forward has a negative x offset
+    pub fn offset(self) -> Vector3<f32> {
+        match self {
+            Hitbox::Octane => Vector3::new(-13.87566, 0.0, 20.75499),
+            Hitbox::Dominus => Vector3::new(-9.0, 0.0, 15.75),
+            Hitbox::Plank => Vector3::new(-9.008572, 0.0, 12.0942),
+            Hitbox::Breakout => Vector3::new(-12.5, 0.0, 11.75),
+            Hitbox::Hybrid => Vector3::new(-13.87566, 0.0, 20.75499),
+            Hitbox::Merc => Vector3::new(-11.37566, 0.0, 21.504988),
+        }
+    }
+
+    /// z position of the car when resting on flat ground
+    pub fn resting_z(self) -> f32 {
+        match self {
+            Hitbox::Octane => 17.01,
+            Hitbox::Dominus => 17.05, // TODO double check
+            Hitbox::Plank => 18.65,
+            Hitbox::Breakout => 18.33, // TODO double check
+            Hitbox::Hybrid => 17.01,   // TODO double check
+            Hitbox::Merc => 17.01,     // TODO double check
+        }
+    }
+
+    pub fn inertia(self) -> na::Matrix3<f32> {
+        // TODO we only know the octane's inertia
+        *CAR_INERTIA
+    }
+
+    pub fn inverse_inertia(self) -> na::Matrix3<f32> {
+        // TODO we only know the octane's inertia
+        *CAR_INVERSE_INERTIA
+    }
+
+    /// the hitbox class with dimensions closest to the given length, width and height
+    pub fn closest(dimensions: &Vector3<f32>) -> Hitbox {
+        let mut closest = Hitbox::default();
+        let mut min_distance = f32::MAX;
+        for hitbox in HITBOXES.iter() {
+            let distance = (hitbox.dimensions() - dimensions).norm();
+            if distance < min_distance {
+                min_distance = distance;
+                closest = *hitbox;
+            }
+        }
+        closest
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoostPad {
+    pub position: Vector3<f32>,
+    pub is_full_boost: bool,
+    pub is_active: bool,
+    /// seconds since the pad was picked up. only meaningful while the pad is inactive
+    pub timer: f32,
+}
+
+impl BoostPad {
+    fn new(x: f32, y: f32, z: f32, is_full_boost: bool) -> BoostPad {
+        BoostPad {
+            position: Vector3::new(x, y, z),
+            is_full_boost,
+            is_active: true,
+            timer: 0.0,
+        }
+    }
+
+    pub fn amount(&self) -> f32 {
+        if self.is_full_boost {
+            FULL_BOOST_PAD_AMOUNT
+        } else {
+            SMALL_BOOST_PAD_AMOUNT
+        }
+    }
+
+    pub fn respawn_time(&self) -> f32 {
+        if self.is_full_boost {
+            FULL_BOOST_PAD_RESPAWN_TIME
+        } else {
+            SMALL_BOOST_PAD_RESPAWN_TIME
+        }
+    }
+
+    /// seconds until this pad can be picked up again, zero if it's already active
+    pub fn time_until_active(&self) -> f32 {
+        if self.is_active {
+            0.0
+        } else {
+            (self.respawn_time() - self.timer).max(0.0)
+        }
+    }
+}
+
+lazy_static! {
+    // source: https://github.com/RLBot/RLBot/wiki/Useful-Game-Values
+    // NOTE these are in the same order as the boost pad states in the tick packet, and x is
+    // already flipped to match our coordinate system (see update_game_state)
+    #[rustfmt::skip]
+    pub static ref SOCCAR_BOOST_PADS: Vec<BoostPad> = vec![
+        BoostPad::new(    0.0, -4240.0, 70.0, false),
+        BoostPad::new( 1792.0, -4184.0, 70.0, false),
+        BoostPad::new(-1792.0, -4184.0, 70.0, false),
+        BoostPad::new( 3072.0, -4096.0, 73.0, true ),
+        BoostPad::new(-3072.0, -4096.0, 73.0, true ),
+        BoostPad::new(  940.0, -3308.0, 70.0, false),
+        BoostPad::new( -940.0, -3308.0, 70.0, false),
+        BoostPad::new(    0.0, -2816.0, 70.0, false),
+        BoostPad::new( 3584.0, -2484.0, 70.0, false),
+        BoostPad::new(-3584.0, -2484.0, 70.0, false),
+        BoostPad::new( 1788.0, -2300.0, 70.0, false),
+        BoostPad::new(-1788.0, -2300.0, 70.0, false),
+        BoostPad::new( 2048.0, -1036.0, 70.0, false),
+        BoostPad::new(    0.0, -1024.0, 70.0, false),
+        BoostPad::new(-2048.0, -1036.0, 70.0, false),
+        BoostPad::new( 3584.0,     0.0, 73.0, true ),
+        BoostPad::new( 1024.0,     0.0, 70.0, false),
+        BoostPad::new(-1024.0,     0.0, 70.0, false),
+        BoostPad::new(-3584.0,     0.0, 73.0, true ),
+        BoostPad::new( 2048.0,  1036.0, 70.0, false),
+        BoostPad::new(    0.0,  1024.0, 70.0, false),
+        BoostPad::new(-2048.0,  1036.0, 70.0, false),
+        BoostPad::new( 1788.0,  2300.0, 70.0, false),
+        BoostPad::new(-1788.0,  2300.0, 70.0, false),
+        BoostPad::new( 3584.0,  2484.0, 70.0, false),
+        BoostPad::new(-3584.0,  2484.0, 70.0, false),
+        BoostPad::new(    0.0,  2816.0, 70.0, false),
+        BoostPad::new(  940.0,  3310.0, 70.0, false),
+        BoostPad::new( -940.0,  3308.0, 70.0, false),
+        BoostPad::new( 3072.0,  4096.0, 73.0, true ),
+        BoostPad::new(-3072.0,  4096.0, 73.0, true ),
+        BoostPad::new( 1792.0,  4184.0, 70.0, false),
+        BoostPad::new(-1792.0,  4184.0, 70.0, false),
+        BoostPad::new(    0.0,  4240.0, 70.0, false),
+    ];
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct BallState {
     pub position: Vector3<f32>,
@@ -243,14 +555,34 @@ pub enum Steer {
     Right,
     Left,
     Straight,
+    /// anything in between, from -1.0 (left) to 1.0 (right). we only have driving models for the
+    /// discrete values, so this gets predicted by interpolating between the two closest ones
+    Analog(f32),
 }
 
+// analog input this close to a discrete value is treated as that value
+const ANALOG_SNAP: f32 = 0.01;
+
 impl Steer {
     pub fn value(self) -> f32 {
         match self {
             Steer::Right => 1.0,
             Steer::Left => -1.0,
             Steer::Straight => 0.0,
+            Steer::Analog(value) => value,
+        }
+    }
+
+    pub fn from_value(value: f32) -> Steer {
+        let value = na::clamp(value, -1.0, 1.0);
+        if value.abs() < ANALOG_SNAP {
+            Steer::Straight
+        } else if value > 1.0 - ANALOG_SNAP {
+            Steer::Right
+        } else if value < -1.0 + ANALOG_SNAP {
+            Steer::Left
+        } else {
+            Steer::Analog(value)
         }
     }
 }
@@ -260,6 +592,9 @@ pub enum Throttle {
     Forward,
     Reverse,
     Idle,
+    /// anything in between, from -1.0 (reverse) to 1.0 (forward). predicted the same way as
+    /// Steer::Analog
+    Analog(f32),
 }
 
 impl Throttle {
@@ -268,6 +603,20 @@ impl Throttle {
             Throttle::Forward => 1.0,
             Throttle::Reverse => -1.0,
             Throttle::Idle => 0.0,
+            Throttle::Analog(value) => value,
+        }
+    }
+
+    pub fn from_value(value: f32) -> Throttle {
+        let value = na::clamp(value, -1.0, 1.0);
+        if value.abs() < ANALOG_SNAP {
+            Throttle::Idle
+        } else if value > 1.0 - ANALOG_SNAP {
+            Throttle::Forward
+        } else if value < -1.0 + ANALOG_SNAP {
+            Throttle::Reverse
+        } else {
+            Throttle::Analog(value)
         }
     }
 }
@@ -314,6 +663,54 @@ pub struct PlanResult {
     pub ball_trajectory: Vec<BallState>,
     pub visualization_lines: Vec<(Point3<f32>, Point3<f32>, Point3<f32>)>,
     pub visualization_points: Vec<(Point3<f32>, Point3<f32>)>,
+    /// the strategy this was planned under, to be kept in BotState
+    pub strategy: Option<Strategy>,
+    /// we ran out of time searching, so the plan only gets part of the way there
+    pub partial: bool,
+    pub search_stats: SearchStats,
+}
+
+/// why the search stopped
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SearchTermination {
+    /// never searched, eg there was nothing to plan for
+    #[default]
+    NotSearched,
+    Reached,
+    /// the ball is too high for us to reach at all
+    Unreachable,
+    MaxCost,
+    MaxIterations,
+    Deadline,
+    /// ran out of vertices to expand
+    Exhausted,
+}
+
+/// counts from a single search, for tuning the search config
+#[derive(Debug, Default, Clone)]
+pub struct SearchStats {
+    pub expansions: u32,
+    /// pushes onto the open set
+    pub pushes: u32,
+    /// entries popped off the open set after a cheaper path to their vertex was already found
+    pub duplicates: u32,
+    /// new vertices dropped since their cell was already taken by a better one
+    pub pruned: u32,
+    /// expansions that the player prediction failed on, eg for lack of driving model samples. also
+    /// counts failed ball hits, and exploding the plan found into single ticks failing
+    pub prediction_failures: u32,
+    /// vertices accepted into the same cell as their parent, see brain::plan::hybrid_a_star
+    pub same_cell_expansions: u32,
+    /// hits rolled forward to see if they score, see predict::ball::trajectory_enters_goal
+    pub shot_checks: u32,
+    pub max_open_set_size: usize,
+    pub heuristic_calls: u32,
+    /// distinct grid cells reached, see CellConfig
+    pub cells: usize,
+    /// total cost of the plan found, if any. along with cells, shows the tradeoff of a finer grid
+    pub plan_cost: Option<f32>,
+    pub duration: Duration,
+    pub termination: SearchTermination,
 }
 
 impl Default for PlanResult {
@@ -326,17 +723,66 @@ impl Default for PlanResult {
             ball_trajectory: vec![],
             visualization_lines: vec![],
             visualization_points: vec![],
+            strategy: None,
+            partial: false,
+            search_stats: SearchStats::default(),
         }
     }
 }
 
 pub struct SearchConfig {
+    /// the step duration when none in step_schedule applies
     pub step_duration: f32,
+    /// coarser or finer steps depending on how far we are from the goal, as (minimum heuristic
+    /// cost, step duration) pairs, checked in order. empty to always use step_duration. step
+    /// durations must have a matching table in the driving model
+    pub step_schedule: Vec<(f32, f32)>,
     pub slop: f32,
     pub max_cost: f32,
     pub max_iterations: i32,
     pub scale_heuristic: f32,
     pub custom_filter: Option<fn(&PlayerState) -> bool>,
+    /// also branch on half steer, which helps on gentle curves that otherwise zig-zag between full
+    /// lock and straight, at the cost of a larger search
+    pub analog_branches: bool,
+    /// extra cost per unit of boost used, in the same units as the plan cost (seconds). zero
+    /// treats boost as free, though plans still can't use more boost than we have
+    pub boost_cost: f32,
+    /// when to give up searching and return the most promising partial plan instead
+    pub deadline: Option<Instant>,
+    pub cell: CellConfig,
+}
+
+/// which dimensions the search grid discretizes player states along, and how finely. paths
+/// ending in the same cell are pruned down to the cheapest one, so finer cells can find better
+/// plans but make for larger searches. None leaves that dimension out of the cell entirely
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellConfig {
+    /// x/y grid size, as a multiple of the distance covered in one step at the current speed
+    pub position_scale: f32,
+    /// z grid size. we only drive on the ground for now, so all heights are the same
+    pub z_size: Option<f32>,
+    /// speed bucket size, so a slow and fast path through the same spot aren't compared
+    pub speed_size: Option<f32>,
+    /// number of buckets in a full turn of yaw
+    pub yaw_buckets: u16,
+    /// number of buckets in a full turn of roll and of pitch, for once we're on walls or in the air
+    pub roll_pitch_buckets: Option<u16>,
+    /// so that running low on boost doesn't get pruned in favour of a path that saved some
+    pub boost_size: Option<f32>,
+}
+
+impl Default for CellConfig {
+    fn default() -> CellConfig {
+        CellConfig {
+            position_scale: 1.0,
+            z_size: None,
+            speed_size: None,
+            yaw_buckets: 16,
+            roll_pitch_buckets: None,
+            boost_size: None,
+        }
+    }
 }
 
 impl Default for SearchConfig {
@@ -346,11 +792,16 @@ impl Default for SearchConfig {
             // and accuracy, can do 8.0 * TICK step duration with 10.0 slop, though that will
             // require a larger max_iterations value to plan some paths
             step_duration: 16.0 * TICK,
+            step_schedule: vec![],
             slop: 20.0,
             max_cost: 10.0,
             max_iterations: 300_000, // 50_000 or lower is more appropriate when using knn heuristic
             scale_heuristic: 1.0,
             custom_filter: None,
+            analog_branches: false,
+            boost_cost: 0.0,
+            deadline: None,
+            cell: CellConfig::default(),
         }
     }
 }
@@ -371,7 +822,7 @@ pub struct DesiredContact {
 impl Default for DesiredContact {
     fn default() -> DesiredContact {
         DesiredContact {
-            position: Vector3::new(0.0, 0.0, RESTING_Z),
+            position: Vector3::new(0.0, 0.0, Hitbox::default().resting_z()),
             heading: Vector3::new(0.0, 1.0, 0.0),
         }
     }
@@ -382,39 +833,62 @@ impl Default for DesiredContact {
 pub fn update_game_state(game_state: &mut GameState, tick: &rlbot::GameTickPacket, player_index: usize, frame: u32) {
     let ball = tick.ball.as_ref().expect("Missing ball");
     let players = &tick.players;
-    let player = players.get(player_index).expect("Missing player");
-
-    let bp = &ball.physics;
-    let bl = &bp.location;
-    let bv = &bp.velocity;
-    let bav = &bp.angular_velocity;
-    game_state.ball.position = Vector3::new(-bl.x, bl.y, bl.z); // x should be positive towards right, it only makes sense
-    game_state.ball.velocity = Vector3::new(-bv.x, bv.y, bv.z); // x should be positive towards right, it only makes sense
-    game_state.ball.angular_velocity = Vector3::new(-bav.x, bav.y, bav.z); // x should be positive towards right, it only makes sense
-
-    let pp = &player.physics;
-    let pl = &pp.location;
-    let pv = &pp.velocity;
-    let pav = &pp.angular_velocity;
-    let pr = &pp.rotation;
-    game_state.player.position = Vector3::new(-pl.x, pl.y, pl.z); // x should be positive towards right, it only makes sense
-    game_state.player.velocity = Vector3::new(-pv.x, pv.y, pv.z); // x should be positive towards right, it only makes sense
-    game_state.player.angular_velocity = Vector3::new(-pav.x, pav.y, pav.z); // x should be positive towards right, it only makes sense
-
-    let uq = UnitQuaternion::from_euler_angles(pr.roll, pr.pitch, pr.yaw);
-    let q = uq.quaternion();
-    // converting from right handed to left handed coordinate system (goes with the x axis flip above)
-    // https://stackoverflow.com/a/34366144/127219
-    game_state.player.rotation =
-        UnitQuaternion::from_quaternion(Quaternion::new(q.scalar(), -q.vector()[0], q.vector()[1], -q.vector()[2]));
+    assert!(players.get(player_index).is_some(), "Missing player");
+
+    game_state.ball.set_physics(&ball.physics);
+    game_state.last_touch = ball.latest_touch.as_ref().map(BallTouch::from);
+
+    game_state.players = players.iter().map(PlayerState::from).collect();
+    game_state.player_index = player_index;
+    game_state.player = game_state.players[player_index].clone();
+
+    let pad_states = &tick.boost_pad_states;
+    if game_state.boost_pads.len() != pad_states.len() && pad_states.len() == SOCCAR_BOOST_PADS.len() {
+        // we weren't given the layout via update_field_info, so assume it's standard soccar
+        game_state.boost_pads = SOCCAR_BOOST_PADS.clone();
+    }
+    for (pad, pad_state) in game_state.boost_pads.iter_mut().zip(pad_states.iter()) {
+        pad.is_active = pad_state.is_active;
+        pad.timer = pad_state.timer;
+    }
+
+    update_match_info(&mut game_state.match_info, tick);
 
     game_state.frame = frame;
+}
 
-    game_state.player.team = match player.team {
-        0 => Team::Blue,
-        1 => Team::Orange,
-        _ => unimplemented!(),
-    };
+fn update_match_info(match_info: &mut MatchInfo, tick: &rlbot::GameTickPacket) {
+    let info = &tick.game_info;
+    match_info.seconds_elapsed = info.seconds_elapsed;
+    match_info.seconds_remaining = info.game_time_remaining.max(0.0);
+    match_info.is_unlimited_time = info.is_unlimited_time;
+    match_info.is_overtime = info.is_overtime;
+    match_info.is_round_active = info.is_round_active;
+    match_info.is_kickoff_pause = info.is_kickoff_pause;
+    match_info.is_match_ended = info.is_match_ended;
+
+    for team in tick.teams.iter() {
+        let score = team.score.max(0) as u32;
+        match team.team_index {
+            0 => match_info.blue_score = score,
+            1 => match_info.orange_score = score,
+            _ => unimplemented!(),
+        }
+    }
+}
+
+/// sets up the arena kind and the boost pad positions and sizes from the field info. the active
+/// state and timers of the pads are then kept up to date by update_game_state
+pub fn update_field_info(game_state: &mut GameState, field_info: &rlbot::FieldInfo) {
+    game_state.arena = ArenaKind::from_field_info(field_info);
+    game_state.boost_pads = field_info
+        .boost_pads
+        .iter()
+        .map(|pad| {
+            let l = vector_from_rlbot(&pad.location);
+            BoostPad::new(l.x, l.y, l.z, pad.is_full_boost)
+        })
+        .collect();
 }
 
 #[cfg(test)]
@@ -464,4 +938,120 @@ mod tests {
         assert!((player.local_velocity().y - -707.1).abs() < 0.1);
         assert!((player.local_velocity().x - 707.1).abs() < 0.1);
     }
+
+    #[test]
+    fn test_teammates_and_opponents() {
+        let mut game = GameState::default();
+        let orange = PlayerState {
+            team: Team::Orange,
+            ..PlayerState::default()
+        };
+        game.players = vec![PlayerState::default(), orange.clone(), PlayerState::default(), orange];
+        game.player_index = 2;
+        game.player = game.players[2].clone();
+
+        let teammates = game.teammates().map(|(i, _)| i).collect::<Vec<_>>();
+        let opponents = game.opponents().map(|(i, _)| i).collect::<Vec<_>>();
+        assert_eq!(teammates, vec![0]);
+        assert_eq!(opponents, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_boost_pad_time_until_active() {
+        let mut pad = SOCCAR_BOOST_PADS[3].clone();
+        assert!(pad.is_full_boost);
+        assert!(pad.time_until_active().abs() < 0.001);
+
+        pad.is_active = false;
+        pad.timer = 4.0;
+        assert!((pad.time_until_active() - 6.0).abs() < 0.001);
+
+        let mut small_pad = SOCCAR_BOOST_PADS[0].clone();
+        small_pad.is_active = false;
+        small_pad.timer = 5.0;
+        assert!(small_pad.time_until_active().abs() < 0.001);
+    }
+
+    #[test]
+    fn test_match_info() {
+        let mut match_info = MatchInfo {
+            blue_score: 1,
+            orange_score: 3,
+            seconds_remaining: 20.0,
+            ..MatchInfo::default()
+        };
+        assert_eq!(match_info.goal_difference(Team::Blue), -2);
+        assert_eq!(match_info.goal_difference(Team::Orange), 2);
+        assert!(match_info.is_ending_soon(30.0));
+        assert!(!match_info.is_ending_soon(10.0));
+
+        match_info.is_overtime = true;
+        assert!(!match_info.is_ending_soon(30.0));
+    }
+
+    #[test]
+    fn test_is_kickoff() {
+        let mut game = GameState {
+            ball: BallState {
+                position: Vector3::new(0.0, 0.0, BALL_COLLISION_RADIUS),
+                ..BallState::default()
+            },
+            ..GameState::default()
+        };
+        assert!(!game.is_kickoff());
+
+        game.match_info.is_kickoff_pause = true;
+        assert!(game.is_kickoff());
+
+        game.ball.position.y = 500.0;
+        assert!(!game.is_kickoff());
+    }
+
+    #[test]
+    fn test_closest_hitbox() {
+        for hitbox in HITBOXES.iter() {
+            assert_eq!(Hitbox::closest(&hitbox.dimensions()), *hitbox);
+        }
+
+        // dominus-like body with slightly different measurements
+        assert_eq!(Hitbox::closest(&Vector3::new(127.0, 83.0, 31.0)), Hitbox::Dominus);
+    }
+
+    #[test]
+    fn test_hitbox_center() {
+        let player = PlayerState {
+            hitbox: Hitbox::Merc,
+            ..PlayerState::default()
+        };
+        // default rotation points towards +y, so forward offset is towards +y
+        let center = player.hitbox_center();
+        assert!((center - Vector3::new(0.0, 11.37566, 21.504988)).norm() < 0.001);
+    }
+
+    #[test]
+    fn test_arena_kind_from_boost_pad_count() {
+        assert_eq!(ArenaKind::from_boost_pad_count(0), ArenaKind::Dropshot);
+        assert_eq!(ArenaKind::from_boost_pad_count(SOCCAR_BOOST_PADS.len()), ArenaKind::Soccar);
+        assert_eq!(ArenaKind::from_boost_pad_count(HOOPS_BOOST_PAD_COUNT), ArenaKind::Hoops);
+    }
+
+    #[test]
+    fn test_analog_controller_values() {
+        assert_eq!(Steer::from_value(0.005), Steer::Straight);
+        assert_eq!(Steer::from_value(-1.5), Steer::Left);
+        assert_eq!(Steer::from_value(0.4), Steer::Analog(0.4));
+        assert_eq!(Throttle::from_value(0.995), Throttle::Forward);
+        assert_eq!(Throttle::from_value(-0.3), Throttle::Analog(-0.3));
+
+        let input = rlbot::ControllerState {
+            steer: 0.3,
+            throttle: -0.6,
+            ..rlbot::ControllerState::default()
+        };
+        let converted = BrickControllerState::from(&input);
+        assert_eq!(converted.throttle, Throttle::Analog(-0.6));
+        let round_tripped = rlbot::ControllerState::from(&converted);
+        assert_eq!(round_tripped.steer, 0.3);
+        assert_eq!(round_tripped.throttle, -0.6);
+    }
 }
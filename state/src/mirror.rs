@@ -0,0 +1,209 @@
+use na::{Point3, UnitQuaternion, Vector3};
+use std::f32::consts::PI;
+
+use crate::*;
+
+/// conversion to and from a team-normalized view of the game, in which we are always on the blue
+/// team and always attacking towards +y. this lets everything downstream be written once, for one
+/// side of the field.
+///
+/// the arena is symmetric under a 180 degree rotation about the z axis, which swaps the two halves
+/// of the field. since that's a rotation and not a reflection, handedness is preserved, so physics
+/// and controller inputs carry over unchanged: steering left is still steering left.
+pub trait Mirror: Clone {
+    /// rotates 180 degrees about the z axis, swapping the blue and orange halves of the field
+    fn mirrored(&self) -> Self;
+
+    /// the view from the given team's perspective, ie as if that team were blue
+    fn normalized(&self, team: Team) -> Self {
+        match team {
+            Team::Blue => self.clone(),
+            Team::Orange => self.mirrored(),
+        }
+    }
+
+    /// inverse of normalized. mirroring twice is a no-op, so this is just the same thing again
+    fn denormalized(&self, team: Team) -> Self {
+        self.normalized(team)
+    }
+}
+
+fn mirror_vector(v: &Vector3<f32>) -> Vector3<f32> {
+    Vector3::new(-v.x, -v.y, v.z)
+}
+
+fn mirror_point(p: &Point3<f32>) -> Point3<f32> {
+    Point3::new(-p.x, -p.y, p.z)
+}
+
+impl Mirror for Team {
+    fn mirrored(&self) -> Team {
+        self.opponent()
+    }
+}
+
+impl Mirror for PlayerState {
+    fn mirrored(&self) -> PlayerState {
+        PlayerState {
+            position: mirror_vector(&self.position),
+            velocity: mirror_vector(&self.velocity),
+            angular_velocity: mirror_vector(&self.angular_velocity),
+            rotation: UnitQuaternion::from_euler_angles(0.0, 0.0, PI) * self.rotation,
+            team: self.team.mirrored(),
+            ..self.clone()
+        }
+    }
+}
+
+impl Mirror for BallState {
+    fn mirrored(&self) -> BallState {
+        BallState {
+            position: mirror_vector(&self.position),
+            velocity: mirror_vector(&self.velocity),
+            angular_velocity: mirror_vector(&self.angular_velocity),
+        }
+    }
+}
+
+impl Mirror for BoostPad {
+    fn mirrored(&self) -> BoostPad {
+        BoostPad {
+            position: mirror_vector(&self.position),
+            ..self.clone()
+        }
+    }
+}
+
+impl Mirror for MatchInfo {
+    fn mirrored(&self) -> MatchInfo {
+        MatchInfo {
+            blue_score: self.orange_score,
+            orange_score: self.blue_score,
+            ..self.clone()
+        }
+    }
+}
+
+impl Mirror for GameState {
+    fn mirrored(&self) -> GameState {
+        GameState {
+            ball: self.ball.mirrored(),
+            player: self.player.mirrored(),
+            players: self.players.iter().map(|p| p.mirrored()).collect(),
+            player_index: self.player_index,
+            boost_pads: self.boost_pads.iter().map(|pad| pad.mirrored()).collect(),
+            arena: self.arena,
+            match_info: self.match_info.mirrored(),
+            last_touch: self.last_touch.as_ref().map(|touch| touch.mirrored()),
+            frame: self.frame,
+        }
+    }
+}
+
+impl Mirror for BallTouch {
+    fn mirrored(&self) -> BallTouch {
+        BallTouch {
+            team: self.team.mirrored(),
+            ..self.clone()
+        }
+    }
+}
+
+impl Mirror for Strategy {
+    fn mirrored(&self) -> Strategy {
+        Strategy {
+            last_touch: self.last_touch.as_ref().map(|touch| touch.mirrored()),
+            ..self.clone()
+        }
+    }
+}
+
+impl Mirror for BrickControllerState {
+    fn mirrored(&self) -> BrickControllerState {
+        // rotations preserve handedness, so the inputs are the same from either side
+        self.clone()
+    }
+}
+
+impl Mirror for FullController {
+    fn mirrored(&self) -> FullController {
+        // rotations preserve handedness, so the inputs are the same from either side
+        self.clone()
+    }
+}
+
+impl Mirror for DesiredContact {
+    fn mirrored(&self) -> DesiredContact {
+        DesiredContact {
+            position: mirror_vector(&self.position),
+            heading: mirror_vector(&self.heading),
+        }
+    }
+}
+
+impl Mirror for PlanResult {
+    fn mirrored(&self) -> PlanResult {
+        PlanResult {
+            plan: self.plan.as_ref().map(|plan| {
+                plan.iter()
+                    .map(|(player, controller, cost)| (player.mirrored(), controller.mirrored(), *cost))
+                    .collect()
+            }),
+            planned_ball: self.planned_ball.as_ref().map(|ball| ball.mirrored()),
+            source_frame: self.source_frame,
+            cost_diff: self.cost_diff,
+            ball_trajectory: self.ball_trajectory.iter().map(|ball| ball.mirrored()).collect(),
+            // the last point of each line is the color, which stays as is
+            visualization_lines: self
+                .visualization_lines
+                .iter()
+                .map(|(start, end, color)| (mirror_point(start), mirror_point(end), *color))
+                .collect(),
+            visualization_points: self
+                .visualization_points
+                .iter()
+                .map(|(point, color)| (mirror_point(point), *color))
+                .collect(),
+            strategy: self.strategy.as_ref().map(|strategy| strategy.mirrored()),
+            partial: self.partial,
+            search_stats: self.search_stats.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirror_orange_attacks_positive_y() {
+        let mut game = GameState::default();
+        game.player.team = Team::Orange;
+        game.player.position = Vector3::new(100.0, -2000.0, 17.0);
+        game.player.velocity = Vector3::new(0.0, -1000.0, 0.0);
+        // facing towards -y, ie towards the blue goal that orange attacks
+        game.player.rotation = UnitQuaternion::from_euler_angles(0.0, 0.0, PI / 2.0);
+        game.players = vec![game.player.clone()];
+        game.match_info.orange_score = 2;
+        game.ball.position = Vector3::new(-50.0, 300.0, 93.0);
+
+        let normalized = game.normalized(Team::Orange);
+        assert_eq!(normalized.player.team, Team::Blue);
+        assert_eq!(normalized.players[0].team, Team::Blue);
+        assert_eq!(normalized.match_info.blue_score, 2);
+        assert!((normalized.player.position - Vector3::new(-100.0, 2000.0, 17.0)).norm() < 0.001);
+        assert!((normalized.player.velocity - Vector3::new(0.0, 1000.0, 0.0)).norm() < 0.001);
+        assert!((normalized.player.heading() - Vector3::new(0.0, 1.0, 0.0)).norm() < 0.001);
+        assert!((normalized.player.local_velocity() - game.player.local_velocity()).norm() < 0.001);
+        assert!((normalized.ball.position - Vector3::new(50.0, -300.0, 93.0)).norm() < 0.001);
+
+        let denormalized = normalized.denormalized(Team::Orange);
+        assert_eq!(denormalized.player.team, Team::Orange);
+        assert!((denormalized.player.position - game.player.position).norm() < 0.001);
+        assert!(denormalized.player.rotation.angle_to(&game.player.rotation) < 0.001);
+
+        // blue is already normalized
+        let blue = GameState::default();
+        assert_eq!(blue.normalized(Team::Blue).player, blue.player);
+    }
+}
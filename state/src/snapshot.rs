@@ -0,0 +1,361 @@
+//! a versioned container for snapshots of the game and our bot at some moment, so that saved
+//! snapshots keep loading as GameState and BotState change.
+//!
+//! the layout is the magic bytes, then the schema version as a bincode u32, then the bincode
+//! snapshot for that version. files written before the container existed are a bare bincode
+//! (GameState, BotState) tuple with no header, and are read as version 0.
+//!
+//! whenever a change to the serialized types breaks reading the current version: freeze copies of
+//! the old types in a new module below, bump SNAPSHOT_VERSION and add an arm to migrate.
+
+use std::error::Error;
+use std::io::{Read, Write};
+
+use crate::*;
+
+pub const SNAPSHOT_MAGIC: [u8; 8] = *b"BRICKSNP";
+pub const SNAPSHOT_VERSION: u32 = 2;
+
+// magic plus the bincode u32 version
+const HEADER_LENGTH: usize = 12;
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Snapshot {
+    pub game: GameState,
+    pub bot: BotState,
+}
+
+// what the json export looks like, so that it's clear which schema it's in
+#[derive(Serialize)]
+struct JsonSnapshot<'a> {
+    version: u32,
+    game: &'a GameState,
+    bot: &'a BotState,
+}
+
+impl Snapshot {
+    pub fn new(game: &GameState, bot: &BotState) -> Snapshot {
+        Snapshot {
+            game: game.clone(),
+            bot: bot.clone(),
+        }
+    }
+
+    pub fn write<W: Write>(&self, writer: &mut W) -> Result<(), Box<dyn Error>> {
+        writer.write_all(&SNAPSHOT_MAGIC)?;
+        bincode::serialize_into(&mut *writer, &SNAPSHOT_VERSION)?;
+        bincode::serialize_into(writer, self)?;
+        Ok(())
+    }
+
+    /// reads a snapshot of any version we know about, migrating it to the current one
+    pub fn read<R: Read>(reader: &mut R) -> Result<Snapshot, Box<dyn Error>> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+
+        if bytes.len() >= HEADER_LENGTH && bytes[..SNAPSHOT_MAGIC.len()] == SNAPSHOT_MAGIC {
+            let version: u32 = bincode::deserialize(&bytes[SNAPSHOT_MAGIC.len()..HEADER_LENGTH])?;
+            migrate(version, &bytes[HEADER_LENGTH..])
+        } else {
+            migrate(0, &bytes)
+        }
+    }
+
+    /// for reading snapshots by eye, or with other tools. this is export only, we always load the
+    /// bincode version
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        let json = JsonSnapshot {
+            version: SNAPSHOT_VERSION,
+            game: &self.game,
+            bot: &self.bot,
+        };
+        Ok(serde_json::to_string_pretty(&json)?)
+    }
+}
+
+fn migrate(version: u32, payload: &[u8]) -> Result<Snapshot, Box<dyn Error>> {
+    match version {
+        0 => Ok(v1::Snapshot::from(bincode::deserialize::<v0::Snapshot>(payload)?).into()),
+        1 => Ok(bincode::deserialize::<v1::Snapshot>(payload)?.into()),
+        SNAPSHOT_VERSION => Ok(bincode::deserialize(payload)?),
+        _ => Err(format!("Unknown snapshot version {}, the latest is {}", version, SNAPSHOT_VERSION).into()),
+    }
+}
+
+/// the headerless snapshots, from back when we only knew about our own car and the ball
+// NOTE types that haven't changed since are used as is. if they do change, freeze a copy here
+pub(crate) mod v0 {
+    use na::{UnitQuaternion, Vector3};
+    use std::collections::VecDeque;
+
+    use crate::{Action, ArenaKind, BallState, BrickControllerState, MatchInfo, Team};
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct PlayerState {
+        pub position: Vector3<f32>,
+        pub velocity: Vector3<f32>,
+        pub angular_velocity: Vector3<f32>,
+        pub rotation: UnitQuaternion<f32>,
+        pub team: Team,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct GameState {
+        pub ball: BallState,
+        pub player: PlayerState,
+        pub frame: u32,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct BotState {
+        pub plan: Option<Vec<(PlayerState, BrickControllerState, f32)>>,
+        pub planned_ball: Option<BallState>,
+        pub plan_source_frame: u32,
+        pub cost_diff: f32,
+        pub controller_history: VecDeque<BrickControllerState>,
+        pub turn_errors: VecDeque<f32>,
+        pub last_action: Option<Action>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct Snapshot {
+        pub game: GameState,
+        pub bot: BotState,
+    }
+
+    impl From<PlayerState> for crate::PlayerState {
+        fn from(player: PlayerState) -> crate::PlayerState {
+            // everything else wasn't recorded, so is the same as what we'd assume for a fennec
+            // driving around with no other information
+            crate::PlayerState {
+                position: player.position,
+                velocity: player.velocity,
+                angular_velocity: player.angular_velocity,
+                rotation: player.rotation,
+                team: player.team,
+                ..crate::PlayerState::default()
+            }
+        }
+    }
+
+    impl From<Snapshot> for super::v1::Snapshot {
+        fn from(snapshot: Snapshot) -> super::v1::Snapshot {
+            let game = snapshot.game;
+            let bot = snapshot.bot;
+            let player = crate::PlayerState::from(game.player);
+
+            super::v1::Snapshot {
+                game: super::v1::GameState {
+                    ball: game.ball,
+                    players: vec![player.clone()],
+                    player,
+                    player_index: 0,
+                    boost_pads: vec![],
+                    arena: ArenaKind::default(),
+                    match_info: MatchInfo::default(),
+                    frame: game.frame,
+                },
+                bot: super::v1::BotState {
+                    plan: bot.plan.map(|plan| {
+                        plan.into_iter()
+                            .map(|(player, controller, cost)| (player.into(), controller, cost))
+                            .collect()
+                    }),
+                    planned_ball: bot.planned_ball,
+                    plan_source_frame: bot.plan_source_frame,
+                    cost_diff: bot.cost_diff,
+                    controller_history: bot.controller_history,
+                    turn_errors: bot.turn_errors,
+                    last_action: bot.last_action,
+                },
+            }
+        }
+    }
+}
+
+/// before we kept track of ball touches, and when we only remembered the last action taken
+pub(crate) mod v1 {
+    use std::collections::VecDeque;
+
+    use crate::{Action, ArenaKind, BallState, BoostPad, BrickControllerState, MatchInfo, Plan, PlayerState, Strategy};
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct GameState {
+        pub ball: BallState,
+        pub player: PlayerState,
+        pub players: Vec<PlayerState>,
+        pub player_index: usize,
+        pub boost_pads: Vec<BoostPad>,
+        pub arena: ArenaKind,
+        pub match_info: MatchInfo,
+        pub frame: u32,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct BotState {
+        pub plan: Option<Plan>,
+        pub planned_ball: Option<BallState>,
+        pub plan_source_frame: u32,
+        pub cost_diff: f32,
+        pub controller_history: VecDeque<BrickControllerState>,
+        pub turn_errors: VecDeque<f32>,
+        pub last_action: Option<Action>,
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug)]
+    pub struct Snapshot {
+        pub game: GameState,
+        pub bot: BotState,
+    }
+
+    impl From<Snapshot> for crate::snapshot::Snapshot {
+        fn from(snapshot: Snapshot) -> crate::snapshot::Snapshot {
+            let game = snapshot.game;
+            let bot = snapshot.bot;
+
+            // the last action is the best guess we have of what we were committed to
+            let strategy = bot.last_action.map(|action| Strategy {
+                action,
+                entry_score: 0.0,
+                started_frame: game.frame,
+                last_touch: None,
+                failed_plans: 0,
+            });
+
+            crate::snapshot::Snapshot {
+                game: crate::GameState {
+                    ball: game.ball,
+                    player: game.player,
+                    players: game.players,
+                    player_index: game.player_index,
+                    boost_pads: game.boost_pads,
+                    arena: game.arena,
+                    match_info: game.match_info,
+                    last_touch: None,
+                    frame: game.frame,
+                },
+                bot: crate::BotState {
+                    plan: bot.plan,
+                    planned_ball: bot.planned_ball,
+                    plan_source_frame: bot.plan_source_frame,
+                    cost_diff: bot.cost_diff,
+                    controller_history: bot.controller_history,
+                    turn_errors: bot.turn_errors,
+                    strategy,
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    fn test_player() -> PlayerState {
+        PlayerState {
+            position: Vector3::new(-1234.0, 2345.0, 300.0),
+            velocity: Vector3::new(500.0, -600.0, 70.0),
+            team: Team::Orange,
+            boost: 42.0,
+            hitbox: Hitbox::Plank,
+            ..PlayerState::default()
+        }
+    }
+
+    fn test_ball() -> BallState {
+        BallState {
+            position: Vector3::new(1000.0, -2000.0, 500.0),
+            velocity: Vector3::new(-300.0, 400.0, -50.0),
+            ..BallState::default()
+        }
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let game = GameState {
+            ball: test_ball(),
+            player: test_player(),
+            players: vec![test_player()],
+            frame: 42,
+            ..GameState::default()
+        };
+        let bot = BotState {
+            plan: Some(vec![(test_player(), BrickControllerState::default(), 0.5)]),
+            strategy: Some(Strategy {
+                action: Action::Shadow,
+                entry_score: 0.5,
+                started_frame: 40,
+                last_touch: None,
+                failed_plans: 1,
+            }),
+            ..BotState::default()
+        };
+
+        let mut bytes = Vec::new();
+        Snapshot::new(&game, &bot).write(&mut bytes).unwrap();
+        let snapshot = Snapshot::read(&mut &bytes[..]).unwrap();
+
+        assert_eq!(snapshot.game.frame, 42);
+        assert_eq!(snapshot.game.player, game.player);
+        assert_eq!(snapshot.game.ball.position, game.ball.position);
+        assert_eq!(snapshot.game.ball.velocity, game.ball.velocity);
+        assert_eq!(snapshot.bot.plan.unwrap()[0].0, test_player());
+        assert_eq!(snapshot.bot.strategy, bot.strategy);
+    }
+
+    #[test]
+    fn test_snapshot_reads_headerless_version_0() {
+        let player = test_player();
+        let legacy_player = v0::PlayerState {
+            position: player.position,
+            velocity: player.velocity,
+            angular_velocity: player.angular_velocity,
+            rotation: player.rotation,
+            team: Team::Orange,
+        };
+        let legacy_game = v0::GameState {
+            ball: test_ball(),
+            player: legacy_player.clone(),
+            frame: 7,
+        };
+        let legacy_bot = v0::BotState {
+            plan: Some(vec![(legacy_player, BrickControllerState::default(), 1.0)]),
+            planned_ball: None,
+            plan_source_frame: 3,
+            cost_diff: 0.0,
+            controller_history: VecDeque::new(),
+            turn_errors: VecDeque::new(),
+            last_action: Some(Action::Shoot),
+        };
+        // exactly what brick used to write
+        let bytes = bincode::serialize(&(&legacy_game, &legacy_bot)).unwrap();
+
+        let snapshot = Snapshot::read(&mut &bytes[..]).unwrap();
+        assert_eq!(snapshot.game.frame, 7);
+        assert_eq!(snapshot.game.player.team, Team::Orange);
+        assert_eq!(snapshot.game.player.position, player.position);
+        assert_eq!(snapshot.game.player.hitbox, Hitbox::Octane);
+        assert_eq!(snapshot.game.players.len(), 1);
+        assert_eq!(snapshot.bot.plan_source_frame, 3);
+        assert_eq!(snapshot.bot.plan.unwrap()[0].0.team, Team::Orange);
+        assert_eq!(snapshot.bot.strategy.unwrap().action, Action::Shoot);
+    }
+
+    #[test]
+    fn test_snapshot_rejects_unknown_version() {
+        let mut bytes = SNAPSHOT_MAGIC.to_vec();
+        bytes.extend(bincode::serialize(&(SNAPSHOT_VERSION + 1)).unwrap());
+        bytes.extend(bincode::serialize(&Snapshot::default()).unwrap());
+        assert!(Snapshot::read(&mut &bytes[..]).is_err());
+    }
+
+    #[test]
+    fn test_snapshot_json_export() {
+        let json = Snapshot::default().to_json().unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], SNAPSHOT_VERSION);
+        assert_eq!(value["game"]["frame"], 0);
+    }
+}